@@ -0,0 +1,26 @@
+// Opponent: Constant acceleration
+// Accelerates in a single random direction the whole time.
+// Tests that we account for acceleration when leading the target.
+
+use oort_api::prelude::*;
+
+pub struct Ship {
+    acceleration: Vec2,
+}
+
+impl Ship {
+    pub fn new() -> Ship {
+        let a = rand(0.2, 1.0) * max_forward_acceleration();
+        let direction = rand(0.0, TAU);
+
+        Ship {
+            acceleration: vec2(a, 0.0).rotate(direction),
+        }
+    }
+
+    pub fn tick(&mut self) {
+        // Face the way we accelerate, as we accelerate fastest going forward
+        turn(angle_diff(heading(), self.acceleration.angle()) * 10.0);
+        accelerate(self.acceleration);
+    }
+}
@@ -0,0 +1,29 @@
+// Opponent: Constant velocity
+// Speeds up in a random direction, then coasts in a straight line.
+// Tests our lead, without any acceleration to account for.
+
+use oort_api::prelude::*;
+
+pub struct Ship {
+    target_velocity: Vec2,
+}
+
+impl Ship {
+    pub fn new() -> Ship {
+        let speed = rand(100.0, 300.0);
+        let direction = rand(0.0, TAU);
+
+        Ship {
+            target_velocity: vec2(speed, 0.0).rotate(direction),
+        }
+    }
+
+    pub fn tick(&mut self) {
+        let dv = self.target_velocity - velocity();
+
+        // Once we are at our speed, stop accelerating
+        if dv.length() > 1.0 {
+            accelerate(dv / TICK_LENGTH);
+        }
+    }
+}
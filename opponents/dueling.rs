@@ -0,0 +1,133 @@
+// Opponent: Dueling
+// A competent fighter: finds the enemy with its radar, leads its shots,
+// and strafes from side to side while keeping at a comfortable range.
+// Tests our targeting and evasion against something that shoots back.
+
+use oort_api::prelude::*;
+
+const BULLET_SPEED: f64 = 1000.0; // m/s
+
+fn degree_to_radian(deg: f64) -> f64 {
+    deg * (PI / 180.0)
+}
+
+pub struct Ship {
+    scan_result: Option<ScanResult>,
+    prev_scan_result: Option<ScanResult>,
+
+    preferred_range: f64,
+    max_range: f64,
+    firing_offset: f64,
+
+    strafe_direction: f64, // 1.0 to strafe left, -1.0 to strafe right
+    next_strafe_change: f64,
+}
+
+impl Ship {
+    pub fn new() -> Ship {
+        Ship {
+            scan_result: None,
+            prev_scan_result: None,
+
+            preferred_range: 1_500.0,
+            max_range: 3_000.0,
+            firing_offset: degree_to_radian(0.5),
+
+            strafe_direction: 1.0,
+            next_strafe_change: 0.0,
+        }
+    }
+
+    fn scan(&mut self) {
+        if let Some(scan) = scan() {
+            // Keep a narrow beam on the target
+            set_radar_heading((scan.position - position()).angle());
+            set_radar_width(degree_to_radian(5.0));
+
+            self.prev_scan_result = std::mem::replace(&mut self.scan_result, Some(scan));
+        } else {
+            // Sweep a wider beam until we find someone
+            set_radar_width(degree_to_radian(20.0));
+            set_radar_heading(radar_heading() + radar_width());
+
+            self.prev_scan_result = None;
+            self.scan_result = None;
+        }
+    }
+
+    // Where the target will be when our bullets get there
+    fn lead(&self, target: &ScanResult) -> Vec2 {
+        let a = match self.prev_scan_result.as_ref() {
+            Some(prev) => (target.velocity - prev.velocity) / TICK_LENGTH,
+            None => vec2(0.0, 0.0),
+        };
+
+        // Relative to us, as our bullets keep our velocity
+        let p0 = target.position - position();
+        let v = target.velocity - velocity();
+
+        let mut t = p0.length() / BULLET_SPEED;
+        for _ in 0..10 {
+            let p1 = p0 + v * t + 0.5 * a * t * t;
+            t = p1.length() / BULLET_SPEED;
+        }
+
+        position() + p0 + v * t + 0.5 * a * t * t
+    }
+
+    // Turn as fast as we can, breaking so we don't overshoot
+    fn turn_to(&self, target_heading: f64) {
+        let diff = angle_diff(heading(), target_heading);
+        let v = angular_velocity();
+        let seconds_to_stop = v.abs() / max_angular_acceleration();
+        let stopping_angle = 0.5 * v * seconds_to_stop;
+
+        if diff.abs() < degree_to_radian(1.0) {
+            turn(diff / TICK_LENGTH);
+        } else if stopping_angle.abs() >= diff.abs() && stopping_angle.signum() == diff.signum() {
+            torque(-v.signum() * max_angular_acceleration());
+        } else {
+            torque(diff.signum() * max_angular_acceleration());
+        }
+    }
+
+    fn maneuver(&mut self, target: &ScanResult) {
+        let to_target = target.position - position();
+        let distance = to_target.length();
+        let towards = to_target / distance;
+        let sideways = towards.rotate(PI / 2.0);
+
+        // Switch sides at random, so we are hard to lead
+        if current_time() >= self.next_strafe_change {
+            self.strafe_direction = -self.strafe_direction;
+            self.next_strafe_change = current_time() + rand(0.5, 1.5);
+        }
+
+        // Close in or back off to stay at our preferred range
+        let range_error = (distance - self.preferred_range) / self.preferred_range;
+        let closing = towards * range_error.clamp(-1.0, 1.0);
+
+        accelerate((sideways * self.strafe_direction + closing) * max_forward_acceleration());
+    }
+
+    pub fn tick(&mut self) {
+        self.scan();
+
+        if let Some(target) = self.scan_result.clone() {
+            let aim = self.lead(&target);
+            let aim_heading = (aim - position()).angle();
+
+            self.turn_to(aim_heading);
+            self.maneuver(&target);
+
+            let in_range = target.position.distance(position()) < self.max_range;
+            let on_target = angle_diff(heading(), aim_heading).abs() < self.firing_offset;
+            if in_range && on_target {
+                fire(0);
+            }
+        } else {
+            // Slow down while we look for the enemy
+            accelerate(-1.0 * velocity());
+        }
+    }
+}
@@ -0,0 +1,35 @@
+// Opponent: Random jinking
+// Changes direction at random times, to throw off anyone leading it.
+// Tests how well we handle targets whose acceleration keeps changing.
+
+use oort_api::prelude::*;
+
+pub struct Ship {
+    acceleration: Vec2,
+    next_jink: f64, // time of the next change in direction
+
+    min_seconds_between_jinks: f64,
+    max_seconds_between_jinks: f64,
+}
+
+impl Ship {
+    pub fn new() -> Ship {
+        Ship {
+            acceleration: vec2(0.0, 0.0),
+            next_jink: 0.0,
+
+            min_seconds_between_jinks: 0.5,
+            max_seconds_between_jinks: 2.0,
+        }
+    }
+
+    pub fn tick(&mut self) {
+        if current_time() >= self.next_jink {
+            self.acceleration = vec2(max_forward_acceleration(), 0.0).rotate(rand(0.0, TAU));
+            self.next_jink = current_time() + rand(self.min_seconds_between_jinks, self.max_seconds_between_jinks);
+        }
+
+        turn(angle_diff(heading(), self.acceleration.angle()) * 10.0);
+        accelerate(self.acceleration);
+    }
+}
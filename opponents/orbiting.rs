@@ -0,0 +1,40 @@
+// Opponent: Orbiting
+// Circles around where it started, always turning.
+// Tests that we can lead a target whose acceleration keeps rotating.
+
+use oort_api::prelude::*;
+
+pub struct Ship {
+    center: Option<Vec2>,
+    radius: f64,
+    speed: f64,
+}
+
+impl Ship {
+    pub fn new() -> Ship {
+        Ship {
+            center: None,
+            radius: rand(500.0, 1_500.0),
+            speed: rand(100.0, 250.0),
+        }
+    }
+
+    pub fn tick(&mut self) {
+        // Orbit around a point `radius` to our left
+        let center = *self.center.get_or_insert_with(|| position() + vec2(0.0, self.radius).rotate(heading()));
+
+        let from_center = position() - center;
+        let distance = from_center.length();
+        let outwards = from_center / distance;
+        let tangent = outwards.rotate(PI / 2.0);
+
+        // The velocity we want: along the circle, plus a correction back onto it
+        let target_velocity = tangent * self.speed - outwards * (distance - self.radius);
+
+        // a = v²/r keeps us turning around the center
+        let centripetal = -1.0 * outwards * (self.speed * self.speed / self.radius);
+
+        accelerate(centripetal + (target_velocity - velocity()));
+        turn(angle_diff(heading(), velocity().angle()) * 10.0);
+    }
+}
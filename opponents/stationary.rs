@@ -0,0 +1,19 @@
+// Opponent: Stationary
+// Holds its position and does nothing else.
+// Useful for checking that our aim works at all.
+
+use oort_api::prelude::*;
+
+pub struct Ship {}
+
+impl Ship {
+    pub fn new() -> Ship {
+        Ship {}
+    }
+
+    pub fn tick(&mut self) {
+        // Cancel out any velocity we started with
+        accelerate(-1.0 * velocity() / TICK_LENGTH);
+        turn(0.0);
+    }
+}
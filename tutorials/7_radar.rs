@@ -19,6 +19,39 @@ fn radian_to_degree(r: f64) -> f64 {
 
 const BULLET_SPEED: f64 = 1000.0; // m/s
 
+// Telemetry
+// Set to false to compile out all debug output and drawing,
+// so it doesn't cost any instructions
+const TELEMETRY: bool = true;
+
+// Colors used when drawing
+const GREEN: u32 = 0x00ff00;
+const CYAN: u32 = 0x47cbe6;
+
+// Only the categories this ship uses, with the same values as in the later tutorials
+#[derive(Clone, Copy)]
+enum Category {
+    Turn = 16,  // torque and heading
+    Track = 64, // the target we are tracking
+    Aim = 128,  // where we are aiming
+}
+
+struct Telemetry {
+    categories: u32, // the enabled categories
+}
+
+impl Telemetry {
+    fn new(categories: &[Category]) -> Telemetry {
+        Telemetry {
+            categories: categories.iter().fold(0, |all, c| all | *c as u32),
+        }
+    }
+
+    fn on(&self, category: Category) -> bool {
+        TELEMETRY && self.categories & category as u32 != 0
+    }
+}
+
 pub struct Ship {
     scan_result: Option<ScanResult>,
     prev_scan_result: Option<ScanResult>,
@@ -26,7 +59,7 @@ pub struct Ship {
     seconds_before_using_turn: f64,
     firing_offset: f64,
 
-    debug_turn: bool,
+    telemetry: Telemetry,
 }
 
 impl Ship {
@@ -38,8 +71,7 @@ impl Ship {
             seconds_before_using_turn: TICK_LENGTH,
             firing_offset: degree_to_radian(0.05),
 
-
-            debug_turn: false,
+            telemetry: Telemetry::new(&[Category::Track, Category::Aim]),
         }
     }
 
@@ -111,7 +143,8 @@ impl Ship {
         let seconds_to_stop = v.abs() / max_angular_acceleration();
 
         // How long it will take us to reach the target heading giving the current v
-        let seconds_to_target_heading = angle_diff / v;
+        // If we aren't spinning at all, we never get there
+        let seconds_to_target_heading = if v != 0.0 { angle_diff / v } else { f64::INFINITY };
 
         // We should start breaking if we would overshoot our target otherwise
         let should_break = 
@@ -120,7 +153,7 @@ impl Ship {
             && seconds_to_stop >= seconds_to_target_heading;
 
         // Info to help us debug
-        if self.debug_turn {
+        if self.telemetry.on(Category::Turn) {
             debug!("target: {}; heading: {};", radian_to_degree(target_heading), radian_to_degree(heading()));
             debug!("degrees to turn: {};", radian_to_degree(angle_diff));
             debug!("v: {}; max a: {};", radian_to_degree(v), radian_to_degree(max_angular_acceleration()));
@@ -167,11 +200,15 @@ impl Ship {
 
             // draws a green line from our ship to the target ship
             // this is useful to visualize what is happening
-            draw_line(position(), target(), 0x00ff00);
+            if self.telemetry.on(Category::Track) {
+                draw_line(position(), target(), GREEN);
+            }
 
             // draws a cyan line to p1 of the target
             // this is where we should be aiming
-            draw_line(position(), p1, 0x47cbe6);
+            if self.telemetry.on(Category::Aim) {
+                draw_line(position(), p1, CYAN);
+            }
 
             // Only fire if we are facing p1
             // Note: everything is in floats, so p1_angle will never be exactly 0
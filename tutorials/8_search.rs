@@ -14,6 +14,40 @@ fn radian_to_degree(r: f64) -> f64 {
 
 const BULLET_SPEED: f64 = 1000.0; // m/s
 
+// Telemetry
+// Set to false to compile out all debug output and drawing,
+// so it doesn't cost any instructions
+const TELEMETRY: bool = true;
+
+// Colors used when drawing
+const GREEN: u32 = 0x00ff00;
+const CYAN: u32 = 0x47cbe6;
+
+// Only the categories this ship uses, with the same values as in the later tutorials
+#[derive(Clone, Copy)]
+enum Category {
+    Fire = 4,   // range and firing arc
+    Turn = 16,  // torque and heading
+    Track = 64, // the target we are tracking
+    Aim = 128,  // where we are aiming
+}
+
+struct Telemetry {
+    categories: u32, // the enabled categories
+}
+
+impl Telemetry {
+    fn new(categories: &[Category]) -> Telemetry {
+        Telemetry {
+            categories: categories.iter().fold(0, |all, c| all | *c as u32),
+        }
+    }
+
+    fn on(&self, category: Category) -> bool {
+        TELEMETRY && self.categories & category as u32 != 0
+    }
+}
+
 pub struct Ship {
     scan_result: Option<ScanResult>,
     prev_scan_result: Option<ScanResult>,
@@ -23,10 +57,13 @@ pub struct Ship {
     max_range: f64,
     max_velocity: f64,
 
-    number_of_ticks_skipped: u64,
+    // Solving where to aim is the expensive part, so while cruising towards a distant target
+    // it's only redone every `cruise_intercept_period` ticks, and reused in between
+    aim_point: Option<Vec2>, // last intercept solution
+    aim_tick: u32,           // tick it was solved on
+    cruise_intercept_period: u32,
 
-    debug_turn: bool,
-    debug_fire: bool,
+    telemetry: Telemetry,
 }
 
 impl Ship {
@@ -40,10 +77,11 @@ impl Ship {
             max_range: 3_000.0,
             max_velocity: 5.0 * max_forward_acceleration(),
 
-            number_of_ticks_skipped: 0,
+            aim_point: None,
+            aim_tick: 0,
+            cruise_intercept_period: 10,
 
-            debug_turn: false,
-            debug_fire: true,
+            telemetry: Telemetry::new(&[Category::Fire, Category::Track, Category::Aim]),
         }
     }
 
@@ -118,7 +156,8 @@ impl Ship {
         let seconds_to_stop = v.abs() / max_angular_acceleration();
 
         // How long it will take us to reach the target heading giving the current v
-        let seconds_to_target_heading = angle_diff / v;
+        // If we aren't spinning at all, we never get there
+        let seconds_to_target_heading = if v != 0.0 { angle_diff / v } else { f64::INFINITY };
 
         // We should start breaking if we would overshoot our target otherwise
         let should_break = 
@@ -127,7 +166,7 @@ impl Ship {
             && seconds_to_stop >= seconds_to_target_heading;
 
         // Info to help us debug
-        if self.debug_turn {
+        if self.telemetry.on(Category::Turn) {
             debug!("target: {}; heading: {};", radian_to_degree(target_heading), radian_to_degree(heading()));
             debug!("degrees to turn: {};", radian_to_degree(angle_diff));
             debug!("v: {}; max a: {};", radian_to_degree(v), radian_to_degree(max_angular_acceleration()));
@@ -170,7 +209,7 @@ impl Ship {
         // how far we need to turn
         let angle_to_turn = angle_diff(heading(), target_heading);
 
-        if self.debug_fire {
+        if self.telemetry.on(Category::Fire) {
             debug!("distance to target: {}", distance_to_target);
             debug!("angle to turn: {}", radian_to_degree(angle_to_turn));
         }
@@ -184,48 +223,56 @@ impl Ship {
     fn intercept_target(&self, target: Vec2) {
         let to_target = target - position();
         
-        let time_to_target = to_target.length() / velocity().length();
+        // If we are stopped, we never get there
+        let time_to_target = if velocity().length() > 0.0 { to_target.length() / velocity().length() } else { f64::INFINITY };
         let time_to_stop = velocity().length() / max_forward_acceleration();
         
         if time_to_target < time_to_stop || to_target.length() < 1_000.0 {
-            // break
-            accelerate(-1.0 * velocity().normalize() * max_forward_acceleration());
+            // break, unless we already have
+            // (`normalize` of a zero vector is NaN)
+            if velocity().length() > 0.0 {
+                accelerate(-1.0 * velocity().normalize() * max_forward_acceleration());
+            }
         } else {
             // accelerate
-            let direction_to_target = (target - position()).normalize();
+            let direction_to_target = to_target / to_target.length();
             accelerate(direction_to_target * max_forward_acceleration());
         }
     }
 
-    fn should_skip_tick(&mut self) -> bool {
+    fn is_cruising(&self) -> bool {
         let distance_to_target = self.scan_result.as_ref().unwrap().position.distance(position());
 
-        // skip if they are out of range and we are max velocity
-        if distance_to_target > self.max_range && velocity().length() >= self.max_velocity && self.number_of_ticks_skipped < 10 {
-            self.number_of_ticks_skipped += 1;
-            return true;
-        } else {
-            self.number_of_ticks_skipped = 0;
-            return false;
-        }
+        // cruising if they are out of range and we are max velocity
+        distance_to_target > self.max_range && velocity().length() >= self.max_velocity
     }
     
     pub fn tick(&mut self) {
         self.scan();
 
-        if self.scan_result.is_some() && !self.should_skip_tick() {
-            let p1 = self.calculate_p1();
+        if self.scan_result.is_some() {
+            let is_due = !self.is_cruising()
+                || current_tick().saturating_sub(self.aim_tick) >= self.cruise_intercept_period;
+            if self.aim_point.is_none() || is_due {
+                self.aim_point = Some(self.calculate_p1());
+                self.aim_tick = current_tick();
+            }
+            let p1 = self.aim_point.unwrap();
             
             // What our heading needs to be to face p1
             let target_heading = (p1 - position()).angle();
 
             // draws a green line from our ship to the target ship
             // this is useful to visualize what is happening
-            draw_line(position(), target(), 0x00ff00);
+            if self.telemetry.on(Category::Track) {
+                draw_line(position(), target(), GREEN);
+            }
 
             // draws a cyan line to p1 of the target
             // this is where we should be aiming
-            draw_line(position(), p1, 0x47cbe6);
+            if self.telemetry.on(Category::Aim) {
+                draw_line(position(), p1, CYAN);
+            }
 
             // Turn to face the target
             self.turn(target_heading);
@@ -235,6 +282,8 @@ impl Ship {
 
             // Fire!
             self.fire(p1);
+        } else {
+            self.aim_point = None;
         }
     }
 }
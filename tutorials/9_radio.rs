@@ -3,9 +3,15 @@
 // 2 will give you its position and velocity.
 
 use oort_api::prelude::*;
+use oort_api::sys::read_system_state;
+use oort_api::SystemState;
+use std::collections::VecDeque;
 
 const BULLET_SPEED: f64 = 1000.0; // m/s
 
+// Math
+// Angle and vector helpers that don't return NaN.
+// Use these rather than calling `acos` or `normalize` directly.
 fn degree_to_radian(deg: f64) -> f64 {
     deg * (PI / 180.0)
 }
@@ -13,6 +19,103 @@ fn radian_to_degree(r: f64) -> f64 {
     r * 180.0 / PI
 }
 
+// Wraps an angle to [-π, π]
+fn normalize_angle(a: f64) -> f64 {
+    angle_diff(0.0, a)
+}
+
+// Rounding can push a cosine just past 1 or -1, where `acos` is NaN
+fn safe_acos(x: f64) -> f64 {
+    if x.is_nan() {
+        return 0.0;
+    }
+    x.clamp(-1.0, 1.0).acos()
+}
+
+// Angle between two vectors, in [0, π]
+// 0 if either has no length, since then there is no angle
+fn angle_between(a: Vec2, b: Vec2) -> f64 {
+    let lengths = a.length() * b.length();
+    if lengths == 0.0 || !lengths.is_finite() {
+        return 0.0;
+    }
+    safe_acos(a.dot(b) / lengths)
+}
+
+// Angle to turn `a` onto `b`, in [-π, π], positive is counterclockwise
+fn signed_angle(a: Vec2, b: Vec2) -> f64 {
+    let cross = a.x * b.y - a.y * b.x;
+    if cross < 0.0 { -angle_between(a, b) } else { angle_between(a, b) }
+}
+
+// Unit vector in the same direction, or zero for a zero vector (where `normalize` gives NaN)
+fn safe_normalize(v: Vec2) -> Vec2 {
+    let length = v.length();
+    if length > 0.0 && length.is_finite() {
+        v / length
+    } else {
+        vec2(0.0, 0.0)
+    }
+}
+
+// The three angles of the triangle a, b, c, at a, b and c
+fn calculate_angles(a: Vec2, b: Vec2, c: Vec2) -> (f64, f64, f64) {
+    (
+        angle_between(b - a, c - a),
+        angle_between(a - b, c - b),
+        angle_between(a - c, b - c),
+    )
+}
+
+// Commands
+// These take the place of oort's commands, and refuse NaN and infinity.
+// A bad value, e.g. from dividing by a speed of zero, is replaced by a safe one,
+// and logged in debug builds so it can be tracked down.
+fn invalid_command(name: &str, value: impl std::fmt::Debug) {
+    if cfg!(debug_assertions) {
+        debug!("{}: invalid value {:?} at tick {}", name, value, current_tick());
+    }
+}
+
+// Falls back to not accelerating
+fn accelerate(a: Vec2) {
+    if a.x.is_finite() && a.y.is_finite() {
+        oort_api::prelude::accelerate(a);
+    } else {
+        invalid_command("accelerate", a);
+        oort_api::prelude::accelerate(vec2(0.0, 0.0));
+    }
+}
+
+// Falls back to no torque
+fn torque(angular_acceleration: f64) {
+    if angular_acceleration.is_finite() {
+        oort_api::prelude::torque(angular_acceleration);
+    } else {
+        invalid_command("torque", angular_acceleration);
+        oort_api::prelude::torque(0.0);
+    }
+}
+
+// Falls back to not turning
+fn turn(speed: f64) {
+    if speed.is_finite() {
+        oort_api::prelude::turn(speed);
+    } else {
+        invalid_command("turn", speed);
+        oort_api::prelude::turn(0.0);
+    }
+}
+
+// Falls back to leaving the radar where it is
+fn set_radar_heading(heading: f64) {
+    if heading.is_finite() {
+        oort_api::prelude::set_radar_heading(heading);
+    } else {
+        invalid_command("set_radar_heading", heading);
+    }
+}
+
 fn estimate_future_position(p0: Vec2, v: Vec2, a: Vec2, dt: f64) -> Vec2 {
     p0 + (v * dt) + (0.5 * a * (dt * dt))
 }
@@ -24,42 +127,195 @@ fn linear(x1: f64, y1: f64, x2: f64, y2: f64) -> Box<dyn Fn (f64) -> f64> {
     Box::new(move |x: f64| slope * x + intercept)
 }
 
+// Telemetry
+// Set to false to compile out all debug output and drawing,
+// so it doesn't cost any instructions
+const TELEMETRY: bool = true;
+
+// Colors used when drawing
+const GREEN: u32 = 0x00ff00;
+const YELLOW: u32 = 0xeaed42;
+const RED: u32 = 0xc2330c;
+const DARK_RED: u32 = 0xbd0416;
+const BLUE: u32 = 0x0037fc;
+const CYAN: u32 = 0x47cbe6;
+const GRAY: u32 = 0x9e9e9e;
+const PINK: u32 = 0xf279ae;
+const LIGHT_PINK: u32 = 0xed85dc;
+const PURPLE: u32 = 0x9c2488;
+const ORANGE: u32 = 0xdb9523;
+
+// Drawing
+// These take the place of oort's drawing functions, so replays can also capture what was drawn
+fn draw_line(a: Vec2, b: Vec2, color: u32) {
+    oort_api::prelude::draw_line(a, b, color);
+
+    #[cfg(not(target_arch = "wasm32"))]
+    DRAWN_LINES.with(|lines| {
+        if let Some(lines) = lines.borrow_mut().as_mut() {
+            lines.push((a, b, color));
+        }
+    });
+}
+
+fn draw_polygon(center: Vec2, radius: f64, sides: i32, angle: f64, color: u32) {
+    let mut p = vec2(radius, 0.0).rotate(angle);
+    for _ in 0..sides {
+        let p2 = p.rotate(TAU / sides as f64);
+        draw_line(center + p, center + p2, color);
+        p = p2;
+    }
+}
+
+fn draw_triangle(center: Vec2, radius: f64, color: u32) {
+    draw_polygon(center, radius, 3, PI / 2.0, color);
+}
+
+fn draw_square(center: Vec2, radius: f64, color: u32) {
+    draw_polygon(center, radius, 4, PI / 4.0, color);
+}
+
+// Only collected while `tools::replay` is capturing a tick, so nothing builds up otherwise
+#[cfg(not(target_arch = "wasm32"))]
+thread_local! {
+    static DRAWN_LINES: std::cell::RefCell<Option<Vec<(Vec2, Vec2, u32)>>> = const { std::cell::RefCell::new(None) };
+}
+
+#[derive(Clone, Copy)]
+enum Category {
+    Scan = 1,        // radar hits and target matching
+    Radio = 2,       // messages sent and received
+    Fire = 4,        // weapon readiness and firing arc
+    Move = 8,        // acceleration
+    Turn = 16,       // torque and heading
+    Intercept = 32,  // where the target will be, and where it could be
+    Track = 64,      // the target we are tracking
+    Aim = 128,       // where we are aiming
+    Record = 256,    // every input read this tick, so it can be replayed
+    Trace = 512,     // a JSON line per tick for plotting
+    Behavior = 1024, // switching between behaviors
+}
+
+struct Telemetry {
+    categories: u32, // the enabled categories
+}
+
+impl Telemetry {
+    fn on(&self, category: Category) -> bool {
+        TELEMETRY && self.categories & category as u32 != 0
+    }
+
+    // Line to the target, and where it's heading
+    fn draw_track(&self, target: &Target) {
+        if self.on(Category::Track) {
+            draw_line(position(), target.position(), GREEN);
+            draw_line(target.position(), target.position() + target.velocity(), GREEN);
+        }
+    }
+
+    fn draw_aim_point(&self, aim: Vec2) {
+        if self.on(Category::Aim) {
+            draw_line(position(), aim, PURPLE);
+            draw_square(aim, 20.0, PURPLE);
+        }
+    }
+
+    // Edges of the arc we are willing to fire in
+    fn draw_firing_arc(&self, heading: f64, half_width: f64, range: f64) {
+        if self.on(Category::Fire) {
+            draw_line(position(), position() + vec2(range, 0.0).rotate(heading + half_width), LIGHT_PINK);
+            draw_line(position(), position() + vec2(range, 0.0).rotate(heading - half_width), LIGHT_PINK);
+        }
+    }
+
+    // Everywhere the target could reach by maneuvering
+    fn draw_envelope(&self, center: Vec2, radius: f64) {
+        if self.on(Category::Intercept) {
+            draw_polygon(center, radius, 16, 0.0, CYAN);
+        }
+    }
+}
+
+// Radar hits get noisier as the signal-to-noise ratio (snr) drops.
+// These settings control how much we trust each hit, and when a new contact
+// is trusted enough to be targeted.
+#[derive(Clone, Debug)]
+pub struct TrackerConfig {
+    pub min_snr: f64,         // hits weaker than this (dB) are treated as false contacts
+    pub confirm_snr: f64,     // a single hit at least this strong (dB) confirms a target straight away
+    pub confirm_hits: usize,  // otherwise a target is confirmed after this many hits
+    pub reference_snr: f64,   // snr (dB) where a hit is off by about `reference_noise`
+    pub reference_noise: f64, // expected position error (m) of a hit at `reference_snr`
+    pub report_noise: f64,    // expected position error (m) of a target shared by a teammate
+}
+
+impl TrackerConfig {
+    // Expected position error of a hit with the given snr
+    // Every 20dB less snr makes the error 10 times larger
+    fn noise(&self, snr: f64) -> f64 {
+        self.reference_noise * 10f64.powf((self.reference_snr - snr) / 20.0)
+    }
+
+    fn is_strong(&self, hit: &ScanHit) -> bool {
+        hit.noise <= self.noise(self.confirm_snr)
+    }
+}
+
+// Most a contact of this class could accelerate, with room for noisy velocities
+fn max_target_acceleration(class: Class) -> f64 {
+    10.0 * class.default_stats().max_forward_acceleration
+}
+
+#[derive(Clone)]
 struct Target {
     last_seen: f64,
     hits: Vec<ScanHit>,
     expire_after: f64, // Number of seconds before this target is no longer valid
+    variance: f64, // How unsure we are of the position estimate (m^2)
+    confirmed: bool, // Tentative targets are tracked, but not fired at
 }
 
 impl Target {
-    fn new(sr: ScanResult) -> Target {
+    fn new(hit: ScanHit, config: &TrackerConfig) -> Target {
         Target {
-            last_seen: current_time(),
-            hits: vec!(ScanHit::new(sr)),
+            last_seen: hit.time,
+            variance: hit.noise * hit.noise,
+            confirmed: config.is_strong(&hit) || config.confirm_hits <= 1,
+            hits: vec!(hit),
             expire_after: (TICK_LENGTH * (degree_to_radian(360.0) / degree_to_radian(10.0))) + TICK_LENGTH * 4.0,
         }
     }
 
-    fn match_last_seen(&self, scan: &ScanResult, debug: bool) -> bool {
+    fn match_last_seen(&self, hit: &ScanHit, debug: bool) -> bool {
+        let scan = &hit.result;
         let last_scan = &self.hits.last().unwrap();
-        let dt = current_time() - last_scan.time;
-        let max_aceleration = 10.0 * max_forward_acceleration();
+        let dt = hit.time - last_scan.time;
+        let max_aceleration = max_target_acceleration(scan.class);
+
+        // Noisy hits can land further away, so give them some slack
+        let slack = 3.0 * self.variance.sqrt();
+
+        // A late report from before our last hit is ours if it's close to what we saw
+        if dt <= 0.0 {
+            return scan.position.distance(last_scan.position) < slack + max_aceleration * TICK_LENGTH;
+        }
         
-        let a = (vec2(scan.velocity.x, scan.velocity.y) - last_scan.result.velocity) / dt;
+        let a = (vec2(scan.velocity.x, scan.velocity.y) - last_scan.velocity) / dt;
         if a.length() > max_aceleration { 
             if debug { debug!("acceleration"); }
             return false 
         }
 
-        let predicted_position = self.future_position(current_time(), true);
+        let predicted_position = self.future_position(hit.time, true);
         let a = (scan.position - self.position()) / dt;
         
         let v = scan.position - self.position();
         let max_v = self.velocity() + vec2(0.0, max_aceleration);
 
         if debug { 
-            draw_triangle(predicted_position, 100.0, 0xeaed42); // yellow
-            draw_square(predicted_position, max_aceleration * dt, 0xeaed42); // yellow
-            draw_triangle(scan.position, 100.0, 0xc2330c); // red
+            draw_triangle(predicted_position, 100.0, YELLOW);
+            draw_square(predicted_position, max_aceleration * dt, YELLOW);
+            draw_triangle(scan.position, 100.0, RED);
     
             debug!("max a: {}", max_aceleration);
             debug!("a: {}", a.length());
@@ -71,9 +327,10 @@ impl Target {
             debug!("dt {}", dt);
             debug!("max v: {}", max_v.length());
             debug!("v: {}", v.length());
+            debug!("slack: {}", slack);
         }
         
-        if v.length() > max_v.length() {
+        if v.length() > max_v.length() + slack {
             if debug { debug!("vel"); }
             return false 
         }
@@ -84,11 +341,11 @@ impl Target {
 
     fn future_position(&self, t: f64, ignore_aceleration: bool) -> Vec2 {
         let scan1 = &self.hits.last().unwrap();
-        let scan0 = &self.hits.get(self.hits.len() - 2);
+        let scan0 = &self.hits.iter().rev().nth(1);
         
         // Hint: p = p₀ + v₀t + ½at² (the third equation of kinematics)
-        let p0 = scan1.result.position;
-        let v = scan1.result.velocity;
+        let p0 = scan1.position;
+        let v = scan1.velocity;
         let a = 
             if ignore_aceleration || scan0.is_none() {vec2(0.0,0.0)} 
             else { scan1.aceleration(scan0.unwrap()) };
@@ -97,24 +354,53 @@ impl Target {
         p0 + (v * dt) + (0.5 * a * (dt * dt))
     }
 
-    fn add_scan(&mut self, sr: ScanResult) {
-        self.last_seen = current_time();
-        self.hits.push(ScanHit::new(sr));
+    fn add_hit(&mut self, mut hit: ScanHit, config: &TrackerConfig) {
+        let last = self.hits.last().unwrap();
+        let dt = hit.time - last.time;
+
+        // Radio reports can arrive late, so we may already know something newer
+        if dt <= 0.0 {
+            return;
+        }
+
+        // Where we expect the target to be, and how unsure we are of that
+        // The longer since the last hit, the more the target could have maneuvered
+        let predicted_position = estimate_future_position(last.position, last.velocity, vec2(0.0, 0.0), dt);
+        let predicted_variance = self.variance + (0.5 * max_target_acceleration(hit.result.class) * dt * dt).powi(2);
+
+        // Blend the prediction with the hit
+        // A noisy hit only nudges the estimate, while a clean hit mostly replaces it
+        let hit_variance = hit.noise * hit.noise;
+        let gain = 
+            if predicted_variance + hit_variance > 0.0 { predicted_variance / (predicted_variance + hit_variance) }
+            else { 1.0 };
+
+        hit.position = predicted_position + (hit.result.position - predicted_position) * gain;
+        hit.velocity = last.velocity + (hit.result.velocity - last.velocity) * gain;
+        self.variance = (1.0 - gain) * predicted_variance;
+
+        self.last_seen = hit.time;
+        self.hits.push(hit);
+        self.confirmed = self.confirmed
+            || config.is_strong(self.hits.last().unwrap())
+            || self.hits.len() >= config.confirm_hits;
     }
 
     fn position(&self) -> Vec2 {
-        let scan1 = &self.hits.last().unwrap();
-        vec2(scan1.result.position.x, scan1.result.position.y)
+        self.hits.last().unwrap().position
     }
 
     fn velocity(&self) -> Vec2 {
-        let scan1 = &self.hits.last().unwrap();
-        vec2(scan1.result.velocity.x, scan1.result.velocity.y)
+        self.hits.last().unwrap().velocity
+    }
+
+    fn class(&self) -> Class {
+        self.hits.last().unwrap().result.class
     }
 
     fn aceleration(&self) -> Vec2 {
         let scan1 = &self.hits.last().unwrap();
-        let scan0 = &self.hits.get(self.hits.len() - 2);
+        let scan0 = &self.hits.iter().rev().nth(1);
 
         if let Some(scan0) = scan0 {
             scan1.aceleration(scan0)
@@ -123,6 +409,11 @@ impl Target {
         }
     }
 
+    // Seconds since the target was last measured
+    fn age(&self) -> f64 {
+        current_time() - self.hits.last().unwrap().time
+    }
+
     fn has_expired(&self) -> bool {
         current_time() - self.last_seen >= self.expire_after
     }
@@ -130,412 +421,3514 @@ impl Target {
 }
 
 //struct ScanResult { position: Vec2, velocity: Vec2 }
+#[derive(Clone)]
 struct ScanHit {
     result: ScanResult,
-    time: f64,
+    time: f64, // when the hit was measured, which can be before we received it
+    noise: f64, // expected position error of this hit (m)
+    shared: bool, // reported by a teammate, rather than seen by us
+
+    // Our best estimate of the target at the time of this hit
+    // Starts as the raw hit, and is smoothed once added to a target
+    position: Vec2,
+    velocity: Vec2,
 }
 impl ScanHit {
-    fn new(result: ScanResult) -> ScanHit {
+    fn new(result: ScanResult, noise: f64, time: f64) -> ScanHit {
         ScanHit {
-            time: current_time(),
+            time,
+            noise,
+            shared: false,
+            position: result.position,
+            velocity: result.velocity,
             result
         }
     }
 
     fn aceleration(&self, hit: &ScanHit) -> Vec2 {
         let dt = self.time - hit.time;
-        (self.result.velocity - hit.result.velocity) / dt
+        (self.velocity - hit.velocity) / dt
     }
 }
 
-pub struct TargetEstimate {
-    estimate_time: f64,
-    created_time: f64, 
-    position: Vec2,
-    angle_error: f64,
+// Radio protocol
+// A radio message is only 4 floats, so each message is packed into whole numbers:
+//   [0] header: version | kind | sender | checksum
+//   [1..3] payload, depending on the kind of message
+// Anything that doesn't unpack, or whose checksum doesn't match, was either
+// corrupted or sent by someone else, and is rejected.
+const PROTOCOL_VERSION: u64 = 1;
+
+const POSITION_RESOLUTION: f64 = 0.5; // m
+const VELOCITY_RESOLUTION: f64 = 0.01; // m/s
+const HALF_BITS: u32 = 24; // bits per x/y when a vector is packed into one float
+
+enum RadioMessage {
+    // A target we are tracking
+    TargetReport { class: Class, position: Vec2, velocity: Vec2, time: f64 },
+    // Where the sender is
+    PositionReport { position: Vec2, velocity: Vec2, heading: f64, time: f64 },
+    // Tells the `recipient` ship to engage the target
+    AttackOrder { recipient: u32, position: Vec2, velocity: Vec2, time: f64 },
+    // The sender is under attack by the `threat`
+    RequestForHelp { class: Class, position: Vec2, threat: Vec2, time: f64 },
 }
 
-pub struct Ship {
-    max_range: f64,
-    closest_target: Option<Target>,
-    distance_to_target: f64,
-    is_weapon_ready: bool,
+struct RadioPacket {
+    sender: u32,
+    message: RadioMessage,
+}
 
-    search: bool,
-    search_start: f64,
-    number_targets: i64,
+#[derive(Debug)]
+enum RadioError {
+    NotPacked,        // one of the floats isn't a packed whole number
+    Version(u64),     // sent with a different version of the protocol
+    Checksum,         // the checksum doesn't match the contents
+    UnknownKind(u64), // the version matches, but we don't know the kind of message
+}
 
-    fire_offset_percent: f64,
-    fire_offset_percent_increment: f64,
+impl RadioError {
+    fn reason(&self) -> String {
+        match self {
+            RadioError::NotPacked => "not packed".to_string(),
+            RadioError::Version(version) => format!("version {}", version),
+            RadioError::Checksum => "bad checksum".to_string(),
+            RadioError::UnknownKind(kind) => format!("unknown kind {}", kind),
+        }
+    }
+}
 
-    ticks_since_last_check: u64,
-    max_v: f64,
+impl RadioMessage {
+    // The kind of message, sent in the header
+    const TARGET_REPORT: u64 = 1;
+    const POSITION_REPORT: u64 = 2;
+    const ATTACK_ORDER: u64 = 3;
+    const REQUEST_FOR_HELP: u64 = 4;
+
+    fn kind(&self) -> u64 {
+        match self {
+            RadioMessage::TargetReport { .. } => RadioMessage::TARGET_REPORT,
+            RadioMessage::PositionReport { .. } => RadioMessage::POSITION_REPORT,
+            RadioMessage::AttackOrder { .. } => RadioMessage::ATTACK_ORDER,
+            RadioMessage::RequestForHelp { .. } => RadioMessage::REQUEST_FOR_HELP,
+        }
+    }
 
-    debug_scan: bool,
-    debug_fire: bool,
-    debug_move: bool,
-    debug_turn: bool,
-    debug_future_target: bool,
-}
+    fn encode(&self, sender: u32) -> Message {
+        let payload = match self {
+            RadioMessage::TargetReport { class, position, velocity, time } => [
+                pack_vec2(*position, POSITION_RESOLUTION),
+                pack_vec2(*velocity, VELOCITY_RESOLUTION),
+                pack_time(*time) << 8 | class_to_u64(*class),
+            ],
+            RadioMessage::PositionReport { position, velocity, heading, time } => [
+                pack_vec2(*position, POSITION_RESOLUTION),
+                pack_vec2(*velocity, VELOCITY_RESOLUTION),
+                pack_time(*time) << 16 | pack_heading(*heading),
+            ],
+            RadioMessage::AttackOrder { recipient, position, velocity, time } => [
+                pack_vec2(*position, POSITION_RESOLUTION),
+                pack_vec2(*velocity, VELOCITY_RESOLUTION),
+                pack_time(*time) << 16 | (*recipient as u64 & 0xffff),
+            ],
+            RadioMessage::RequestForHelp { class, position, threat, time } => [
+                pack_vec2(*position, POSITION_RESOLUTION),
+                pack_vec2(*threat, POSITION_RESOLUTION),
+                pack_time(*time) << 8 | class_to_u64(*class),
+            ],
+        };
+
+        let header = PROTOCOL_VERSION << 40 | self.kind() << 32 | (sender as u64 & 0xffff) << 16;
+        let checksum = radio_checksum(header, payload);
+
+        [
+            (header | checksum) as f64,
+            payload[0] as f64,
+            payload[1] as f64,
+            payload[2] as f64,
+        ]
+    }
 
-impl Ship {
-    pub fn new() -> Ship {
-        Ship {
-            max_range: 3_000.0,
-            closest_target: None,
-            distance_to_target: 0.0,
-            is_weapon_ready: false,
+    fn decode(msg: Message) -> Result<RadioPacket, RadioError> {
+        let mut words = [0u64; 4];
+        for (i, v) in msg.iter().enumerate() {
+            words[i] = unpack_word(*v).ok_or(RadioError::NotPacked)?;
+        }
 
-            search: false,
-            search_start: 0.0,
-            number_targets: 0,
+        let header = words[0] & !0xffff;
+        let payload = [words[1], words[2], words[3]];
 
-            fire_offset_percent: 0.0,
-            fire_offset_percent_increment: 0.2,
+        let version = header >> 40;
+        if version != PROTOCOL_VERSION {
+            return Err(RadioError::Version(version));
+        }
+        if words[0] & 0xffff != radio_checksum(header, payload) {
+            return Err(RadioError::Checksum);
+        }
+
+        let kind = (header >> 32) & 0xff;
+        let sender = ((header >> 16) & 0xffff) as u32;
+        let message = match kind {
+            RadioMessage::TARGET_REPORT => RadioMessage::TargetReport {
+                position: unpack_vec2(payload[0], POSITION_RESOLUTION),
+                velocity: unpack_vec2(payload[1], VELOCITY_RESOLUTION),
+                time: unpack_time(payload[2] >> 8),
+                class: Class::from_f64((payload[2] & 0xff) as f64),
+            },
+            RadioMessage::POSITION_REPORT => RadioMessage::PositionReport {
+                position: unpack_vec2(payload[0], POSITION_RESOLUTION),
+                velocity: unpack_vec2(payload[1], VELOCITY_RESOLUTION),
+                time: unpack_time(payload[2] >> 16),
+                heading: unpack_heading(payload[2] & 0xffff),
+            },
+            RadioMessage::ATTACK_ORDER => RadioMessage::AttackOrder {
+                position: unpack_vec2(payload[0], POSITION_RESOLUTION),
+                velocity: unpack_vec2(payload[1], VELOCITY_RESOLUTION),
+                time: unpack_time(payload[2] >> 16),
+                recipient: (payload[2] & 0xffff) as u32,
+            },
+            RadioMessage::REQUEST_FOR_HELP => RadioMessage::RequestForHelp {
+                position: unpack_vec2(payload[0], POSITION_RESOLUTION),
+                threat: unpack_vec2(payload[1], POSITION_RESOLUTION),
+                time: unpack_time(payload[2] >> 8),
+                class: Class::from_f64((payload[2] & 0xff) as f64),
+            },
+            _ => return Err(RadioError::UnknownKind(kind)),
+        };
+
+        Ok(RadioPacket { sender, message })
+    }
+}
 
-            ticks_since_last_check: 0,
-            max_v: 6.0 * max_forward_acceleration(),
+// Floats can hold whole numbers up to 2^53 exactly
+fn unpack_word(v: f64) -> Option<u64> {
+    if v.is_finite() && v >= 0.0 && v.fract() == 0.0 && v < (1u64 << 53) as f64 {
+        Some(v as u64)
+    } else {
+        None
+    }
+}
 
-            debug_scan: false,
-            debug_fire: false,
-            debug_move: false,
-            debug_turn: false,
-            debug_future_target: false,
+// FNV-1a hash of the header and payload, folded down to 16 bits
+fn radio_checksum(header: u64, payload: [u64; 3]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for word in [header, payload[0], payload[1], payload[2]] {
+        for byte in word.to_le_bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
         }
     }
+    (hash ^ (hash >> 16) ^ (hash >> 32) ^ (hash >> 48)) & 0xffff
+}
+
+fn pack_half(v: f64, resolution: f64) -> u64 {
+    let offset = (1u64 << (HALF_BITS - 1)) as f64;
+    let max = ((1u64 << HALF_BITS) - 1) as f64;
+    ((v / resolution).round() + offset).clamp(0.0, max) as u64
+}
+
+fn unpack_half(q: u64, resolution: f64) -> f64 {
+    (q as f64 - (1u64 << (HALF_BITS - 1)) as f64) * resolution
+}
+
+fn pack_vec2(v: Vec2, resolution: f64) -> u64 {
+    pack_half(v.x, resolution) << HALF_BITS | pack_half(v.y, resolution)
+}
+
+fn unpack_vec2(w: u64, resolution: f64) -> Vec2 {
+    let mask = (1u64 << HALF_BITS) - 1;
+    vec2(unpack_half(w >> HALF_BITS, resolution), unpack_half(w & mask, resolution))
+}
+
+// Times are sent as a tick number
+fn pack_time(t: f64) -> u64 {
+    (t / TICK_LENGTH).round().clamp(0.0, u32::MAX as f64) as u64
+}
+
+fn unpack_time(ticks: u64) -> f64 {
+    ticks as f64 * TICK_LENGTH
+}
+
+fn pack_heading(h: f64) -> u64 {
+    ((h.rem_euclid(TAU) / TAU) * 65536.0).round() as u64 % 65536
+}
+
+fn unpack_heading(q: u64) -> f64 {
+    q as f64 / 65536.0 * TAU
+}
+
+fn class_to_u64(class: Class) -> u64 {
+    match class {
+        Class::Fighter => 0,
+        Class::Frigate => 1,
+        Class::Cruiser => 2,
+        Class::Asteroid => 3,
+        Class::Target => 4,
+        Class::Missile => 5,
+        Class::Torpedo => 6,
+        Class::Unknown => 7,
+    }
+}
 
-    fn skip_tick(&mut self) -> bool {
-        // oort only allows each ship 1,000,000 instructions
-        // this allows as to skip intensive calculations at times
-        // thereby saving instructions to be used when we are closer to our target
-        let should_skip = self.distance_to_target > self.max_range * 1.3
-        && self.ticks_since_last_check < 5
-        && velocity().length() >= self.max_v;
+// Radio manager
+// Each radio can only be on one channel per tick, and a new channel takes effect next tick.
+// The manager takes turns between channels, so every ship running this code
+// is on the same channel on the same tick.
+//
+// Use it by:
+//  - `subscribe` to the channels you want to hear
+//  - `receive` at the start of the tick, then read `messages`
+//  - `publish` messages during the tick
+//  - `transmit` at the end of the tick
+struct RadioManager {
+    radios: usize,                              // number of radios on this ship
+    channels: Vec<usize>,                       // channels we take turns on
+    dedicated: Vec<usize>,                      // channels with a radio to themselves, on radios 0, 1, ...
+    outgoing: Vec<(usize, VecDeque<(u64, Message)>)>, // (kind, message) waiting for their channel's turn
+    listening: Vec<Option<usize>>,              // the channel each radio was set to last tick
+    messages: Vec<(usize, Message)>,            // (channel, message) received this tick
+}
 
-        if should_skip {
-            self.ticks_since_last_check = self.ticks_since_last_check + 1;
-        } else {
-            self.ticks_since_last_check = 0;
+impl RadioManager {
+    fn new(radios: usize) -> RadioManager {
+        RadioManager {
+            radios,
+            channels: Vec::new(),
+            dedicated: Vec::new(),
+            outgoing: Vec::new(),
+            listening: vec![None; radios],
+            messages: Vec::new(),
         }
-        should_skip
     }
 
-    fn radio(&mut self) {
-        set_radio_channel(2);
-        if let Some(msg) = receive() {
-            let p = vec2(msg[0], msg[1]);
-            self.distance_to_target = (p - position()).length();
-            let s = ScanResult {
-                position: p,
-                velocity: vec2(msg[2], msg[3]),
-                rssi: 0.0,
-                snr: 0.0,
-                class: Class::Fighter,
-            };
+    fn subscribe(&mut self, channel: usize) {
+        if !self.channels.contains(&channel) && !self.dedicated.contains(&channel) {
+            self.channels.push(channel);
+        }
+    }
 
-            if self.closest_target.is_none() {
-                self.closest_target = Some(Target::new(s));
-            } else {
-                let t = self.closest_target.as_mut().unwrap();
-                t.add_scan(s);
-            }
+    // Keeps a radio on this channel every tick, so nothing sent on it is missed
+    // At least one radio is left to take turns on the other channels, so with
+    // a single radio the channel just takes turns like any other
+    fn dedicate(&mut self, channel: usize) {
+        if self.dedicated.contains(&channel) || self.channels.contains(&channel) {
+            return;
+        }
+        if self.dedicated.len() + 1 < self.radios {
+            self.dedicated.push(channel);
         } else {
-            debug!("No message!");
+            self.subscribe(channel);
         }
     }
 
-    fn scan(&mut self) {
-        if self.debug_scan { 
-            debug!("Number of targets: {}", self.number_targets);
+    // Queues a message to be sent on the channel's next turn
+    // We also have to be on the channel to send, so this subscribes to it
+    // Each kind of message has one place in the queue, and a newer one replaces what's there,
+    // so a report sent every tick can't push out one that's only sent now and then
+    fn publish(&mut self, channel: usize, msg: &RadioMessage) {
+        self.subscribe(channel);
+
+        let kind = msg.kind();
+        let msg = msg.encode(id());
+        let queue = match self.outgoing.iter().position(|(c, _)| *c == channel) {
+            Some(i) => &mut self.outgoing[i].1,
+            None => {
+                self.outgoing.push((channel, VecDeque::new()));
+                &mut self.outgoing.last_mut().unwrap().1
+            }
+        };
+
+        match queue.iter_mut().find(|(k, _)| *k == kind) {
+            Some(queued) => queued.1 = msg,
+            None => queue.push_back((kind, msg)),
         }
+    }
 
-        // Process scan
-        if let Some(s) = scan() {
-            if self.closest_target.is_some() {
-                let t = self.closest_target.as_mut().unwrap();
-                if t.match_last_seen(&s, self.debug_scan) {
-                    t.add_scan(s);
-                } else if s.position.distance(position()) < t.position().distance(position()) {
-                    self.number_targets += 1;
-                    self.closest_target = Some(Target::new(s));
+    // A message of this kind is waiting for the channel's turn
+    fn is_queued(&self, channel: usize, kind: u64) -> bool {
+        self.outgoing
+            .iter()
+            .any(|(c, queue)| *c == channel && queue.iter().any(|(k, _)| *k == kind))
+    }
+
+    // Reads what every radio heard since last tick
+    fn receive(&mut self) {
+        self.messages.clear();
+
+        for r in 0..self.radios {
+            if let Some(channel) = self.listening[r] {
+                select_radio(r);
+                if let Some(msg) = receive() {
+                    self.messages.push((channel, msg));
                 }
-            } else {
-                self.number_targets += 1;
-                self.closest_target = Some(Target::new(s));
             }
         }
+    }
 
-        // Remove expired target
-        if self.closest_target.as_ref().is_some() && self.closest_target.as_ref().unwrap().has_expired() {
-            self.closest_target = None;
-        }
+    fn messages(&self) -> &[(usize, Message)] {
+        &self.messages
+    }
 
-        // Move rader
-        if self.search {
-            if radar_heading() < degree_to_radian(10.0) && self.closest_target.is_some() {
-                self.search = false;
+    // Moves each radio to its channel for this tick, and sends anything queued for it
+    fn transmit(&mut self) {
+        // Radios after the dedicated ones take turns on the other channels
+        let shared_radios = self.radios - self.dedicated.len();
+
+        for r in 0..self.radios {
+            let channel = if r < self.dedicated.len() {
+                self.dedicated[r]
+            } else if self.channels.is_empty() {
+                continue;
             } else {
-                self.search_start = self.search_start + degree_to_radian(10.0);
-                set_radar_heading(self.search_start);
-            }
-        } else if self.closest_target.is_some() {
-            let fp = self.closest_target.as_ref()
-                .unwrap()
-                .future_position(current_time() + TICK_LENGTH, false);
+                let turn = current_tick() as usize * shared_radios + (r - self.dedicated.len());
+                self.channels[turn % self.channels.len()]
+            };
 
-            set_radar_heading((fp - position()).angle());
+            select_radio(r);
+            set_radio_channel(channel);
+            self.listening[r] = Some(channel);
 
-            if position().distance(fp) < 100.0 {
-                set_radar_width(degree_to_radian(120.0));
-            } else {
-                set_radar_width(degree_to_radian(30.0));
+            if let Some((_, queue)) = self.outgoing.iter_mut().find(|(c, _)| *c == channel) {
+                if let Some((_, msg)) = queue.pop_front() {
+                    send(msg);
+                }
             }
-        } else {
-            self.search = true;
-            self.search_start = 10.0;
-            set_radar_heading(self.search_start);
-            set_radar_width(degree_to_radian(10.0));
         }
     }
+}
 
-    fn calc_future_target(&self, depth_of_calc: u32) -> TargetEstimate {
-        // Hint: p = p₀ + v₀t + ½at² (the third equation of kinematics)
-        let target = self.closest_target.as_ref().unwrap();
-        
-        let mut t = (target.position() - position()).length() / BULLET_SPEED;
-        let mut pf = estimate_future_position(target.position(), target.velocity(), vec2(0.0,0.0), t);
+// Scheduler
+// oort only allows each ship 1,000,000 instructions
+// so expensive tasks only run every few ticks, and the cheap control code
+// (turning, moving, firing) reuses their last result in between.
+// While cruising towards a distant target they run even less often.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Task {
+    Association, // matching radar hits to targets
+    Intercept,   // solving where to aim
+    Envelope,    // checking range and firing arc
+    Assignment,  // agreeing with the fleet on who shoots at what
+}
 
-        // TODO: maybe just have some percent offset for t, instead of recalculating
-        for _ in [..depth_of_calc] {
-            t = (pf - position()).length() / BULLET_SPEED;
-            pf = estimate_future_position(target.position(), target.velocity(), target.aceleration(), t);
+struct TaskSchedule {
+    task: Task,
+    period: u32,        // run every this many ticks
+    cruise_period: u32, // run every this many ticks while cruising
+    last_run: Option<u32>,
+
+    // Counted for profiling
+    runs: u32,
+    skips: u32,
+}
+
+impl TaskSchedule {
+    fn new(task: Task, period: u32, cruise_period: u32) -> TaskSchedule {
+        TaskSchedule {
+            task,
+            period,
+            cruise_period,
+            last_run: None,
+            runs: 0,
+            skips: 0,
         }
+    }
+}
 
-        let ninety_deg_angle = (position() - target.position()).rotate(degree_to_radian(90.0)).normalize();
-        let offset_a = ninety_deg_angle * max_forward_acceleration();
-        let pf_offset = estimate_future_position(target.position(), target.velocity(), offset_a, t);
-        let pf_offset2 = estimate_future_position(target.position(), target.velocity(), -1.0 * offset_a, t);
-        
-        // let pf = vec2((pf_offset.x + pf_offset2.x) / 2.0, (pf_offset.y + pf_offset2.y) / 2.0);
+struct Scheduler {
+    tasks: Vec<TaskSchedule>,
+    cruising: bool,
+    profile: bool, // show how often each task runs
+}
 
-        let m = position();
-        let blue = m - pf;
-        let red = m - pf_offset;
-        let gray = pf - pf_offset;
-        
-        // yellow green angle
-        let Y = pf_offset - target.position();
-        let G = position() - target.position();
-        let yg = (Y.dot(G) / (Y.length() * G.length())).acos();
+impl Scheduler {
+    // Call at the start of each tick
+    fn update(&mut self, cruising: bool) {
+        self.cruising = cruising;
 
-        let angles = calculate_angles(m, pf, pf_offset);
+        if TELEMETRY && self.profile {
+            debug!("cruising: {}", cruising);
+            for s in &self.tasks {
+                debug!("{:?}: ran {}; skipped {};", s.task, s.runs, s.skips);
+            }
+        }
+    }
 
-        let deg_blue_gray = radian_to_degree(angles.1);
-        let deb_blue_red = radian_to_degree(angles.0);
-        let deb_red_gray = radian_to_degree(angles.2);
-        let deg_total = deg_blue_gray + deb_blue_red + deb_red_gray;
-        
-        let angles2 = calculate_angles(m, pf, pf_offset2);
-        
-        let angle_error = angles.0.max(angles2.0).max(degree_to_radian(1.0));
+    // `stale` forces the task to run, e.g. when there's no earlier result to reuse
+    fn should_run(&mut self, task: Task, stale: bool) -> bool {
+        let cruising = self.cruising;
+        let Some(s) = self.tasks.iter_mut().find(|s| s.task == task) else {
+            // Tasks without a schedule always run
+            return true;
+        };
+
+        let period = if cruising { s.cruise_period } else { s.period };
+        let is_due = match s.last_run {
+            Some(tick) => current_tick().saturating_sub(tick) >= period,
+            None => true,
+        };
+
+        if stale || is_due {
+            s.last_run = Some(current_tick());
+            s.runs += 1;
+            true
+        } else {
+            s.skips += 1;
+            false
+        }
+    }
+}
 
+#[derive(Clone, Copy)]
+pub struct TargetEstimate {
+    estimate_time: f64,
+    created_time: f64, 
+    position: Vec2,
+    angle_error: f64,
+}
 
-        if self.debug_future_target {
-            debug!("(blue) m - pf = {}", blue.length());
-            debug!("(red)  m - pfo = {}", red.length());
-            debug!("(gray) pf - pfo = {}", gray.length());
-            debug!("deg_blue_red = {deb_blue_red}");
-            debug!("deg_blue_gray = {deg_blue_gray}");
-            debug!("deg_red_gray = {deb_red_gray}");
-            debug!("deg_total = {deg_total}");
-            debug!("angle_error = {}", radian_to_degree(angle_error));
+// Overrides baked in when the ship is bundled, written in a small part of TOML:
+//
+//   max_range = 2500
+//   debug_scan = true
+//
+//   [frigate]
+//   seconds_before_using_turn = 2.0
+//
+// Keys before any [section] apply to every class, keys under a section only
+// to that class. A bundler can paste the contents of a `ship.toml` here.
+//
+// In the Radio tutorial the game sends the enemy's position on channel 2.
+// Other scenarios don't, so bundle without this line for them.
+const CONFIG_OVERRIDES: &str = "raw_radio_channel = 2";
+
+// Weapons
+// Each class has its own guns, with their own bullet speed, range and mount.
+// Missile launchers aren't guns, so they aren't listed here.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mount {
+    Fixed,  // points where the ship points, so we aim by turning
+    Turret, // aimed on its own with `aim`
+}
 
-            
-            draw_line(m, pf, 0x0037fc); // blue
-            draw_line(m, pf_offset, 0xbd0416); // red
-            draw_line(pf, pf_offset, 0x9e9e9e); // gray
+#[derive(Clone, Copy, Debug)]
+pub struct Weapon {
+    pub index: usize,      // what to pass to `fire`, `aim` and `reload_ticks`
+    pub bullet_speed: f64, // m/s
+    pub range: f64,        // furthest this weapon is fired from (m)
+    pub mount: Mount,
+    pub turn_rate: f64,    // how fast a turret turns (radians/s)
+}
 
-            draw_line(pf, pf_offset2, 0x9e9e9e); // gray
-            draw_line(m, pf_offset2, 0xbd0416); // red
+// Roughly the guns each class has in the game
+pub fn weapons_for_class(class: Class) -> Vec<Weapon> {
+    let fixed = |index, bullet_speed, range| Weapon { index, bullet_speed, range, mount: Mount::Fixed, turn_rate: 0.0 };
+    let turret = |index, bullet_speed, range, turn_rate| Weapon { index, bullet_speed, range, mount: Mount::Turret, turn_rate };
+
+    match class {
+        Class::Frigate => vec![
+            fixed(0, 4000.0, 8_000.0), // main gun
+            turret(1, 1000.0, 3_000.0, degree_to_radian(360.0)),
+            turret(2, 1000.0, 3_000.0, degree_to_radian(360.0)),
+        ],
+        Class::Cruiser => vec![turret(0, 2000.0, 5_000.0, degree_to_radian(180.0))],
+        _ => vec![fixed(0, BULLET_SPEED, 3_000.0)],
+    }
+}
 
+// Missile and torpedo launchers
+// What they launch runs `guide_missile`, all we decide is when to launch
+#[derive(Clone, Copy, Debug)]
+pub struct Launcher {
+    pub index: usize,      // what to pass to `fire` and `reload_ticks`
+    pub class: Class,      // Missile or Torpedo
+    pub acceleration: f64, // m/s^2
+    pub flight_time: f64,  // how long it can chase the target (s)
+}
 
-            debug!("deg yellow green = {}", radian_to_degree(yg));
-            draw_line(target.position(), pf, 0xf279ae); // pink
-            draw_line(target.position(), pf_offset, 0xe3e31b); // yellow
-            draw_line(target.position(), target.position() + (offset_a * 1000.0), 0xdb9523); // orange
-        }
-        
-        TargetEstimate {
-            estimate_time: current_time() + t,
-            created_time: current_time(), 
-            position: pf,
-            angle_error,
-        }
+// Roughly the launchers each class has in the game
+pub fn launchers_for_class(class: Class) -> Vec<Launcher> {
+    let launcher = |index, class: Class, flight_time| {
+        let acceleration = class.default_stats().max_forward_acceleration;
+        Launcher { index, class, acceleration, flight_time }
+    };
+    let missile = |index| launcher(index, Class::Missile, 10.0);
+    let torpedo = |index| launcher(index, Class::Torpedo, 30.0);
+
+    match class {
+        Class::Fighter => vec![missile(1)],
+        Class::Frigate => vec![missile(3)],
+        Class::Cruiser => vec![missile(1), missile(2), torpedo(3)],
+        _ => vec![],
     }
+}
 
-    fn get_fire_offset(&mut self, angle_error: f64) -> f64 {
-        let offset = angle_error * self.fire_offset_percent;
-        
-        // Update offset if we can fire this tick
-        if self.is_weapon_ready {
-            self.fire_offset_percent = self.fire_offset_percent + self.fire_offset_percent_increment;
-            if self.fire_offset_percent >= 1.0 || self.fire_offset_percent <= -1.0 {
-                self.fire_offset_percent_increment = -1.0 * self.fire_offset_percent_increment;
+// What we know about a weapon while fighting
+struct WeaponState {
+    weapon: Weapon,
+    estimate: Option<TargetEstimate>, // where this weapon's bullets meet the target
+    ready: bool,                      // fire this tick
+
+    // The game doesn't tell us where a turret points, so we follow it ourselves
+    aim_heading: f64,   // where we told the turret to point
+    turret_angle: f64,  // where we think it points, relative to our heading
+    on_solution: bool,  // turret points close enough to `estimate` to fire
+}
+
+// Everything we might want to change without editing the code.
+// Defaults depend on the class of ship, then `CONFIG_OVERRIDES` is applied,
+// then the environment, e.g. `max_range=2500`, so a test or sweep can try
+// different values
+#[derive(Clone, Debug)]
+pub struct ShipConfig {
+    pub max_range: f64,                     // furthest we will fire from (m)
+    pub firing_offset: f64,                 // smallest firing arc (radians)
+    pub fire_offset_percent_increment: f64, // how fast we sweep shots across the firing arc
+    pub seconds_before_using_turn: f64,     // switch from torque to turn when this close to our heading
+    pub passing_speed: f64,                 // speed we slow down to when reaching the target (m/s)
+    pub max_speed: f64,                     // at this speed, far from the target, we are cruising (m/s)
+    pub evade_health: f64,                  // evade once health drops below this part of what we started with
+    pub behavior_tree: bool,                // decide with `default_tree` instead of the `Behavior` state machine
+    pub tree_max_nodes: usize,              // most behavior tree nodes we visit in a tick
+
+    pub tracker: TrackerConfig,
+
+    pub weapons: Vec<Weapon>,
+
+    // Missiles and torpedoes
+    pub nav_constant: f64,   // proportional navigation gain, usually 3 to 5
+    pub augmented_nav: bool, // also steer for the target's acceleration
+    pub blast_radius: f64,   // explode when we'll pass the target closer than this (m)
+    pub launchers: Vec<Launcher>,
+    pub max_missiles_per_target: usize, // don't launch more than this at the same target
+    pub launch_arc: f64,                // launch only when the target is this close to our heading (radians)
+    pub point_defense_time: f64,        // shoot at missiles that will hit us sooner than this (s)
+    pub point_defense_radius: f64,      // a missile passing closer than this is going to hit us (m)
+
+    // Fleets
+    pub position_report_period: u32, // ticks between telling the team where we are
+    pub target_report_period: u32,   // ticks between telling the team about an enemy
+    pub leader_timeout: f64,         // a teammate we haven't heard from in this long is gone (s)
+    pub formation_spacing: f64,      // distance between ships in the formation (m)
+    pub max_ships_per_target: usize, // more than this on one target is overkill
+
+    pub use_radar: bool,                  // turn off to save instructions on a ship without a working radar
+    pub radios: usize,                    // how many radios we use
+    pub team_channel: usize,              // channel we share targets with our team on
+    pub raw_radio_channel: Option<usize>, // channel that sends plain [x, y, vx, vy], not our protocol
+
+    pub telemetry: u32, // enabled telemetry categories
+    pub profile: bool,  // log how often each task runs and is skipped
+}
+
+impl ShipConfig {
+    pub const KEYS: [&'static str; 43] = [
+        "max_range",
+        "firing_offset",
+        "fire_offset_percent_increment",
+        "seconds_before_using_turn",
+        "passing_speed",
+        "max_speed",
+        "evade_health",
+        "behavior_tree",
+        "tree_max_nodes",
+        "nav_constant",
+        "augmented_nav",
+        "blast_radius",
+        "max_missiles_per_target",
+        "launch_arc",
+        "point_defense_time",
+        "point_defense_radius",
+        "position_report_period",
+        "target_report_period",
+        "leader_timeout",
+        "formation_spacing",
+        "max_ships_per_target",
+        "min_snr",
+        "confirm_snr",
+        "confirm_hits",
+        "reference_snr",
+        "reference_noise",
+        "report_noise",
+        "use_radar",
+        "radios",
+        "team_channel",
+        "raw_radio_channel", // a negative channel turns it off
+        "profile",
+        "debug_scan",
+        "debug_radio",
+        "debug_fire",
+        "debug_move",
+        "debug_turn",
+        "debug_intercept",
+        "debug_track",
+        "debug_aim",
+        "debug_record",
+        "debug_trace",
+        "debug_behavior",
+    ];
+
+    pub fn for_class(class: Class) -> ShipConfig {
+        let mut config = ShipConfig {
+            max_range: 3_000.0,
+            firing_offset: degree_to_radian(1.0),
+            fire_offset_percent_increment: 0.2,
+            seconds_before_using_turn: 1.0,
+            passing_speed: 200.0,
+            max_speed: 6.0 * max_forward_acceleration(),
+            evade_health: 0.25,
+            behavior_tree: false,
+            tree_max_nodes: 32,
+
+            tracker: TrackerConfig {
+                min_snr: 3.0,
+                confirm_snr: 15.0,
+                confirm_hits: 3,
+                reference_snr: 20.0,
+                reference_noise: 10.0,
+                report_noise: 5.0,
+            },
+
+            weapons: weapons_for_class(class),
+
+            nav_constant: 4.0,
+            augmented_nav: true,
+            blast_radius: 100.0,
+            launchers: launchers_for_class(class),
+            max_missiles_per_target: 2,
+            launch_arc: degree_to_radian(60.0),
+            point_defense_time: 5.0,
+            point_defense_radius: 200.0,
+
+            position_report_period: 10,
+            target_report_period: 4,
+            leader_timeout: 1.0,
+            formation_spacing: 200.0,
+            max_ships_per_target: 2,
+
+            use_radar: true,
+            radios: 2,
+            team_channel: 5,
+            raw_radio_channel: None,
+
+            telemetry: Category::Track as u32 | Category::Aim as u32,
+            profile: false,
+        };
+
+        // Bigger ships turn slower and have longer range guns
+        match class {
+            Class::Frigate => {
+                config.max_range = 5_000.0;
+                config.seconds_before_using_turn = 2.0;
+                config.passing_speed = 100.0;
             }
+            Class::Cruiser => {
+                config.max_range = 6_000.0;
+                config.seconds_before_using_turn = 3.0;
+                config.passing_speed = 50.0;
+            }
+            _ => {}
         }
-        
-        offset
+        config
     }
-    
-    fn turn(&mut self, target: &TargetEstimate) {
-        // rand offset, to help account for them changing acceleration
-        let jitter = self.get_fire_offset(target.angle_error);
 
-        let target_angle = (target.position - position()).angle();
-        let target_angle_with_jitter = target_angle + jitter;
-        let angle_diff = angle_diff(
-            heading(), 
-            target_angle_with_jitter
-        );
+    // The defaults for our class, with the bundled and environment overrides
+    pub fn load() -> ShipConfig {
+        let mut config = ShipConfig::for_class(class());
+        config.apply_toml(CONFIG_OVERRIDES, class());
+        config.apply_environment();
+        config
+    }
 
-        let v = angular_velocity();
-        let seconds_to_stop = v.abs() / max_angular_acceleration();
-        let seconds_to_target = angle_diff / v;
+    pub fn apply_toml(&mut self, toml: &str, class: Class) {
+        let class_name = format!("{:?}", class).to_lowercase();
+        let mut section: Option<String> = None;
 
-        let should_break = 
-            seconds_to_stop > 0.0 
-            && seconds_to_target > 0.0
-            && seconds_to_stop >= seconds_to_target;
+        for line in toml.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                section = Some(line[1..line.len() - 1].trim().to_lowercase());
+                continue;
+            }
+            if section.as_ref().is_some_and(|s| *s != class_name) {
+                continue;
+            }
+            let Some((name, value)) = line.split_once('=') else {
+                debug!("config: can't read line: {}", line);
+                continue;
+            };
+            match parse_config_value(value) {
+                Some(value) if self.set(name.trim(), value) => {}
+                _ => debug!("config: can't set {}", line),
+            }
+        }
+    }
 
-        if self.debug_turn {
-                
-            debug!("target: {}; heading: {};", radian_to_degree(target_angle), radian_to_degree(heading()));
-            debug!("jitter: {}; diff: {};", radian_to_degree(jitter), radian_to_degree(angle_diff));
-            debug!("v: {}; max a: {};", radian_to_degree(v), radian_to_degree(max_angular_acceleration()));
-            debug!("to stop: {}; to target: {}; break: {}", seconds_to_stop, seconds_to_target, should_break);
+    pub fn apply_environment(&mut self) {
+        for name in ShipConfig::KEYS {
+            if let Some(value) = oort_api::sys::getenv(name).and_then(parse_config_value) {
+                self.set(name, value);
+            }
         }
+    }
 
-        if should_break {
-            // break!
-            if v > 0.0 {
-                torque(-1.0 * max_angular_acceleration());
-            } else {
-                torque(max_angular_acceleration());
+    // The value `set` would take, or None if there is no such setting
+    pub fn get(&self, name: &str) -> Option<f64> {
+        let flag = |on: bool| if on { 1.0 } else { 0.0 };
+        let value = match name {
+            "max_range" => self.max_range,
+            "firing_offset" => self.firing_offset,
+            "fire_offset_percent_increment" => self.fire_offset_percent_increment,
+            "seconds_before_using_turn" => self.seconds_before_using_turn,
+            "passing_speed" => self.passing_speed,
+            "max_speed" => self.max_speed,
+            "evade_health" => self.evade_health,
+            "behavior_tree" => flag(self.behavior_tree),
+            "tree_max_nodes" => self.tree_max_nodes as f64,
+            "nav_constant" => self.nav_constant,
+            "augmented_nav" => flag(self.augmented_nav),
+            "blast_radius" => self.blast_radius,
+            "max_missiles_per_target" => self.max_missiles_per_target as f64,
+            "launch_arc" => self.launch_arc,
+            "point_defense_time" => self.point_defense_time,
+            "point_defense_radius" => self.point_defense_radius,
+            "position_report_period" => self.position_report_period as f64,
+            "target_report_period" => self.target_report_period as f64,
+            "leader_timeout" => self.leader_timeout,
+            "formation_spacing" => self.formation_spacing,
+            "max_ships_per_target" => self.max_ships_per_target as f64,
+            "min_snr" => self.tracker.min_snr,
+            "confirm_snr" => self.tracker.confirm_snr,
+            "confirm_hits" => self.tracker.confirm_hits as f64,
+            "reference_snr" => self.tracker.reference_snr,
+            "reference_noise" => self.tracker.reference_noise,
+            "report_noise" => self.tracker.report_noise,
+            "use_radar" => flag(self.use_radar),
+            "radios" => self.radios as f64,
+            "team_channel" => self.team_channel as f64,
+            "raw_radio_channel" => self.raw_radio_channel.map_or(-1.0, |c| c as f64),
+            "profile" => flag(self.profile),
+            _ => {
+                let category = name.strip_prefix("debug_").and_then(category_from_name)?;
+                flag(self.telemetry & category as u32 != 0)
+            }
+        };
+        Some(value)
+    }
+
+    // Returns false if there is no such setting
+    pub fn set(&mut self, name: &str, value: f64) -> bool {
+        match name {
+            "max_range" => self.max_range = value,
+            "firing_offset" => self.firing_offset = value,
+            "fire_offset_percent_increment" => self.fire_offset_percent_increment = value,
+            "seconds_before_using_turn" => self.seconds_before_using_turn = value,
+            "passing_speed" => self.passing_speed = value,
+            "max_speed" => self.max_speed = value,
+            "evade_health" => self.evade_health = value,
+            "behavior_tree" => self.behavior_tree = value != 0.0,
+            "tree_max_nodes" => self.tree_max_nodes = value.max(1.0) as usize,
+            "nav_constant" => self.nav_constant = value,
+            "augmented_nav" => self.augmented_nav = value != 0.0,
+            "blast_radius" => self.blast_radius = value,
+            "max_missiles_per_target" => self.max_missiles_per_target = value.max(0.0) as usize,
+            "launch_arc" => self.launch_arc = value,
+            "point_defense_time" => self.point_defense_time = value,
+            "point_defense_radius" => self.point_defense_radius = value,
+            "position_report_period" => self.position_report_period = value.max(1.0) as u32,
+            "target_report_period" => self.target_report_period = value.max(1.0) as u32,
+            "leader_timeout" => self.leader_timeout = value,
+            "formation_spacing" => self.formation_spacing = value,
+            "max_ships_per_target" => self.max_ships_per_target = value.max(1.0) as usize,
+            "min_snr" => self.tracker.min_snr = value,
+            "confirm_snr" => self.tracker.confirm_snr = value,
+            "confirm_hits" => self.tracker.confirm_hits = value.max(1.0) as usize,
+            "reference_snr" => self.tracker.reference_snr = value,
+            "reference_noise" => self.tracker.reference_noise = value,
+            "report_noise" => self.tracker.report_noise = value,
+            "use_radar" => self.use_radar = value != 0.0,
+            "radios" => self.radios = value.clamp(1.0, 8.0) as usize,
+            "team_channel" => self.team_channel = value.max(0.0) as usize,
+            "raw_radio_channel" => self.raw_radio_channel = (value >= 0.0).then_some(value as usize),
+            "profile" => self.profile = value != 0.0,
+            _ => {
+                let Some(category) = name.strip_prefix("debug_").and_then(category_from_name) else {
+                    return false;
+                };
+                if value != 0.0 {
+                    self.telemetry |= category as u32;
+                } else {
+                    self.telemetry &= !(category as u32);
+                }
             }
+        }
+        true
+    }
+}
+
+// Numbers, or true/false which become 1/0
+fn parse_config_value(value: &str) -> Option<f64> {
+    match value.trim().trim_matches('"') {
+        "true" => Some(1.0),
+        "false" => Some(0.0),
+        v => v.parse().ok(),
+    }
+}
+
+fn category_from_name(name: &str) -> Option<Category> {
+    match name {
+        "scan" => Some(Category::Scan),
+        "radio" => Some(Category::Radio),
+        "fire" => Some(Category::Fire),
+        "move" => Some(Category::Move),
+        "turn" => Some(Category::Turn),
+        "intercept" => Some(Category::Intercept),
+        "track" => Some(Category::Track),
+        "aim" => Some(Category::Aim),
+        "record" => Some(Category::Record),
+        "trace" => Some(Category::Trace),
+        "behavior" => Some(Category::Behavior),
+        _ => None,
+    }
+}
+
+// What the ship is trying to do.
+// Each tick `next_behavior` decides if we should switch, then `tick` runs the current one.
+// To add a behavior, add it here, say when to switch to it in `next_behavior`,
+// and what it does in `run_behavior`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Behavior {
+    Search,            // no target, look for one
+    Approach,          // target is out of range, close the distance
+    Engage,            // target is in range, shoot at it
+    Evade,             // badly damaged, stop shooting and dodge
+    ReturnToFormation, // no target, go back to and hold our place in the formation
+    PointDefense,      // a missile or torpedo is coming at us, shoot it down
+}
+
+fn is_missile() -> bool {
+    matches!(class(), Class::Missile | Class::Torpedo)
+}
+
+// A teammate we've heard from over radio
+struct Wingman {
+    id: u32,
+    position: Vec2,
+    velocity: Vec2,
+    heading: f64,
+    time: f64, // when they sent it
+}
+
+// Behavior tree
+// An alternative to `Behavior` for when the decisions get too tangled for a state machine.
+// Each tick the tree is walked from the root, and each node reports back how it went.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TreeStatus {
+    Success,
+    Failure,
+    Running, // ran out of nodes for this tick
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Condition {
+    ThreatIncoming, // a missile or torpedo will hit us within `point_defense_time`
+    HasTarget,
+    TargetInRange,
+    Damaged,     // below `evade_health`
+    HasFormation, // we are following a leader
+}
+
+// Leaves that do something, wrapping what `Ship` can already do
+#[derive(Clone, Copy, Debug)]
+enum Action {
+    Track, // update the intercept solution, fails without a target
+    Turn,
+    Fire,
+    Launch,
+    PointDefense,
+    Move,
+    Brake,
+    Evade,
+    ReturnToFormation,
+}
+
+enum Node {
+    Sequence(Vec<Node>), // runs children in order, stops at the first that doesn't succeed
+    Selector(Vec<Node>), // runs children in order, stops at the first that doesn't fail
+    Invert(Box<Node>),   // swaps success and failure
+    Succeed(Box<Node>),  // runs the child, but always succeeds
+    Condition(Condition),
+    Action(Action),
+}
+
+// Does the same as the `Behavior` state machine
+fn default_tree() -> Node {
+    use Node::*;
+
+    Selector(vec![
+        Sequence(vec![
+            Condition(self::Condition::ThreatIncoming),
+            Action(self::Action::PointDefense),
+        ]),
+        Sequence(vec![
+            Condition(self::Condition::HasTarget),
+            Condition(self::Condition::Damaged),
+            Action(self::Action::Evade),
+        ]),
+        Sequence(vec![
+            Action(self::Action::Track),
+            Action(self::Action::Turn),
+            Succeed(Box::new(Sequence(vec![
+                Condition(self::Condition::TargetInRange),
+                Action(self::Action::Fire),
+            ]))),
+            Action(self::Action::Launch),
+            Action(self::Action::Move),
+        ]),
+        Sequence(vec![
+            // Like `next_behavior`, only go back to formation with nothing to fight
+            Invert(Box::new(Condition(self::Condition::HasTarget))),
+            Condition(self::Condition::HasFormation),
+            Action(self::Action::ReturnToFormation),
+        ]),
+        Action(self::Action::Brake),
+    ])
+}
+
+pub struct Ship {
+    config: ShipConfig,
+    closest_target: Option<Target>,
+    tentative_target: Option<Target>, // a new contact that is not yet trusted
+    pending_hits: Vec<ScanHit>,       // radar hits waiting to be matched to targets
+    target_estimate: Option<TargetEstimate>, // last intercept solution, reused between runs
+    weapons: Vec<WeaponState>,
+    threat_solutions: bool,       // `target_estimate` and the weapons' estimates are for a threat, from point defense
+    missiles_in_flight: Vec<f64>, // when each missile we launched at `missile_target` gives up
+    missile_target: i64,          // `number_targets` when we launched, so we know if the target changed
+
+    search: bool,
+    search_start: f64,
+    number_targets: i64,
+
+    fire_offset_percent: f64,
+    fire_offset_percent_increment: f64,
+
+    scheduler: Scheduler,
+
+    radio: RadioManager,
+
+    behavior: Behavior,
+    behavior_start: f64,    // time we switched to the current behavior
+    starting_health: f64,
+    last_health: f64,        // health at the end of the last tick, to see when we are hit
+    formation: Option<Vec2>, // where we should be when there is nothing to fight
+    formation_velocity: Vec2,
+    wingmen: Vec<Wingman>,   // teammates we've heard from lately
+    leader: Option<u32>,     // who we follow, None when we lead
+    enemies: Vec<Target>,    // enemy ships seen by us or the team
+    next_shared_enemy: usize, // the enemy in `enemies` to tell the team about next
+    assigned: bool,          // our target was picked by `assign_targets`
+    threats: Vec<Target>,    // missiles and torpedoes we can see
+    tree: Option<Node>,      // used instead of `behavior` when set
+
+    telemetry: Telemetry,
+}
+
+impl Ship {
+    pub fn new() -> Ship {
+        Ship::with_config(ShipConfig::load())
+    }
+
+    // Lets a test build a ship with exactly the settings it wants
+    pub fn with_config(config: ShipConfig) -> Ship {
+        let ship = Ship {
+            closest_target: None,
+            tentative_target: None,
+            pending_hits: Vec::new(),
+            target_estimate: None,
+            weapons: config
+                .weapons
+                .iter()
+                .map(|&weapon| WeaponState {
+                    weapon,
+                    estimate: None,
+                    ready: false,
+                    aim_heading: heading(),
+                    turret_angle: 0.0,
+                    on_solution: false,
+                })
+                .collect(),
+            threat_solutions: false,
+
+            missiles_in_flight: Vec::new(),
+            missile_target: 0,
+
+            search: false,
+            search_start: 0.0,
+            number_targets: 0,
+
+            fire_offset_percent: 0.0,
+            fire_offset_percent_increment: config.fire_offset_percent_increment,
+
+            scheduler: Scheduler {
+                tasks: vec![
+                    TaskSchedule::new(Task::Association, 1, 5),
+                    TaskSchedule::new(Task::Intercept, 1, 5),
+                    TaskSchedule::new(Task::Envelope, 1, 5),
+                    TaskSchedule::new(Task::Assignment, 10, 10),
+                ],
+                cruising: false,
+                profile: config.profile,
+            },
+
+            radio: RadioManager::new(config.radios),
+
+            behavior: Behavior::Search,
+            behavior_start: current_time(),
+            starting_health: health(),
+            last_health: health(),
+            formation: None,
+            formation_velocity: vec2(0.0, 0.0),
+            wingmen: Vec::new(),
+            leader: None,
+            enemies: Vec::new(),
+            next_shared_enemy: 0,
+            assigned: false,
+            threats: Vec::new(),
+            tree: if config.behavior_tree { Some(default_tree()) } else { None },
+
+            telemetry: Telemetry { categories: config.telemetry },
+
+            config,
+        };
+
+        if ship.telemetry.on(Category::Record) {
+            debug!("{}", record_config(&ship.config));
+        }
+        ship
+    }
+
+    fn is_cruising(&self) -> bool {
+        // Far from the target and already at top speed, so there's little to decide
+        // This lets us save instructions to be used when we are closer to our target
+        if let Some(t) = self.closest_target.as_ref() {
+            t.position().distance(position()) > self.config.max_range * 1.3
+                && velocity().length() >= self.config.max_speed
         } else {
-            let a = max_angular_acceleration();
-            if seconds_to_target < 1.0 {
-                turn(target_angle - heading());
+            false
+        }
+    }
+
+    fn radio(&mut self) {
+        // Listen to the game's channel and to our team
+        if let Some(raw) = self.config.raw_radio_channel {
+            self.radio.dedicate(raw);
+        }
+        self.radio.subscribe(self.config.team_channel);
+
+        self.radio.receive();
+        let messages = self.radio.messages().to_vec();
+
+        if messages.is_empty() && self.telemetry.on(Category::Radio) {
+            debug!("No message!");
+        }
+
+        for (channel, msg) in messages {
+            match RadioMessage::decode(msg) {
+                Ok(packet) => self.handle_radio(packet),
+                Err(e) if self.config.raw_radio_channel == Some(channel) => {
+                    if self.telemetry.on(Category::Radio) { debug!("raw message ({})", e.reason()); }
+
+                    // The game sends the target's position and velocity as plain floats
+                    // There's no timestamp, but it was sent last tick
+                    // Anyone can send on the channel, so it's tracked like a radar hit, and
+                    // has to match the target, or be confirmed, before we aim with it
+                    let s = ScanResult {
+                        position: vec2(msg[0], msg[1]),
+                        velocity: vec2(msg[2], msg[3]),
+                        rssi: 0.0,
+                        snr: 0.0,
+                        class: Class::Fighter,
+                    };
+                    let mut hit = ScanHit::new(s, self.config.tracker.report_noise, current_time() - TICK_LENGTH);
+                    hit.shared = true;
+                    self.track(hit);
+                }
+                Err(e) => {
+                    if self.telemetry.on(Category::Radio) { debug!("rejected message on {}: {}", channel, e.reason()); }
+                }
             }
+        }
 
-            if angle_diff > 0.0 {
-                torque(a);
-            } else {
-                torque(-1.0 * a);
+        // Missiles only listen, the ships that launched them run the fleet
+        if is_missile() {
+            return;
+        }
+
+        self.share_targets();
+        self.report_position();
+        self.update_fleet();
+
+        if self.scheduler.should_run(Task::Assignment, false) {
+            self.assign_targets();
+        }
+    }
+
+    // Focus fire
+    // Every ship works out the same assignment from the same shared tracks and position reports,
+    // so the fleet agrees on targets without anyone handing them out.
+    // Enemies closest to the fleet come first, and each gets the `max_ships_per_target` ships
+    // closest to it, so fire is concentrated without piling everyone onto one target.
+    // Once every enemy has its share, the remaining ships start again from the first enemy.
+    // Enemies are compared where they are now, not where each ship last saw them.
+    // Ties are broken by rounded positions and then by id, so small differences between
+    // what each ship heard don't change the answer.
+    fn assign_targets(&mut self) {
+        if self.wingmen.is_empty() || self.enemies.is_empty() {
+            self.assigned = false;
+            return;
+        }
+
+        // Rounded to 100m, so ships with slightly different tracks still agree
+        let round = |v: Vec2| ((v.x / 100.0).round() as i64, (v.y / 100.0).round() as i64);
+        let rounded_distance = |a: Vec2, b: Vec2| (a.distance(b) / 100.0).round() as i64;
+
+        let me = id() & 0xffff;
+        let mut ships: Vec<(u32, Vec2)> = self
+            .wingmen
+            .iter()
+            .map(|w| (w.id, w.position + w.velocity * (current_time() - w.time)))
+            .collect();
+        ships.push((me, position()));
+        ships.sort_by_key(|s| s.0);
+
+        let center = ships.iter().fold(vec2(0.0, 0.0), |sum, s| sum + s.1) / ships.len() as f64;
+        let now = current_time();
+        let mut enemies: Vec<Vec2> =
+            self.enemies.iter().filter(|t| t.confirmed).map(|t| t.future_position(now, true)).collect();
+        if enemies.is_empty() {
+            self.assigned = false;
+            return;
+        }
+        enemies.sort_by_key(|&e| (rounded_distance(e, center), round(e)));
+
+        let mut mine = None;
+        'assign: while !ships.is_empty() {
+            for &enemy in enemies.iter() {
+                ships.sort_by_key(|s| (rounded_distance(s.1, enemy), s.0));
+                let count = self.config.max_ships_per_target.min(ships.len());
+                for (ship_id, _) in ships.drain(..count) {
+                    if ship_id == me {
+                        mine = Some(enemy);
+                        break 'assign;
+                    }
+                }
+                if ships.is_empty() {
+                    break 'assign;
+                }
+            }
+        }
+
+        let Some(enemy) = mine else {
+            self.assigned = false;
+            return;
+        };
+        if self.telemetry.on(Category::Radio) {
+            debug!("assigned target: {}", enemy);
+        }
+
+        // Switch to the assigned enemy, unless we are already on it
+        let already_on_it = self
+            .closest_target
+            .as_ref()
+            .is_some_and(|t| rounded_distance(t.future_position(now, true), enemy) <= 1);
+        if !already_on_it {
+            let assigned = self.enemies.iter().find(|t| t.future_position(now, true) == enemy).cloned();
+            if assigned.is_some() {
+                self.number_targets += 1;
+                self.closest_target = assigned;
             }
         }
+        self.assigned = true;
     }
-    
-    fn fire(&self, _target: &TargetEstimate) {
-        if self.is_weapon_ready {
-            fire(0);
+
+    // Every few ticks, let the team know where we are
+    // Ships are spread over different ticks, so they don't all talk at once
+    fn report_position(&mut self) {
+        if (current_tick() + id()) % self.config.position_report_period != 0 {
+            return;
         }
+
+        let report = RadioMessage::PositionReport {
+            position: position(),
+            velocity: velocity(),
+            heading: heading(),
+            time: current_time(),
+        };
+        self.radio.publish(self.config.team_channel, &report);
     }
 
-    fn move_ship(&self, target: &TargetEstimate) {
-        let dp = target.position - position();
-        
-        let heading_normilized = vec2(1.0, 0.0).rotate(heading());
-        let v = velocity();
-        let v_normalized = velocity().normalize(); // this is NaN if v = 0
-        let target_v_normalized = dp.normalize();
-        let v_normalized_diff = target_v_normalized - v_normalized;
-        
-        let dv =
-        if v.length() > 0.0 { (heading_normilized + v_normalized_diff) * max_forward_acceleration() }
-        else { heading_normilized * max_forward_acceleration() };
+    // Leader election
+    // The ship with the lowest id we've heard from lately leads, and everyone else follows.
+    // If the leader stops reporting, it's forgotten after `leader_timeout`, and the next lowest takes over.
+    // Every ship hears the same reports, so they all agree without having to vote.
+    fn update_fleet(&mut self) {
+        let timeout = self.config.leader_timeout;
+        self.wingmen.retain(|w| current_time() - w.time < timeout);
 
-        const passing_speed: f64 = 200.0;
-        let seconds_to_accelerate_to_passing_speed = passing_speed / max_forward_acceleration();
-        let seconds_to_stop = v.length() / max_forward_acceleration();
-        let seconds_to_passing_speed = seconds_to_stop - seconds_to_accelerate_to_passing_speed;
-        let seconds_to_intercept = dp.length() / v.length();
-        let should_break = seconds_to_passing_speed >= seconds_to_intercept;
-        
-        if self.debug_move {
-            debug!("should break: {}", should_break);
-            debug!("v: {}", velocity());
-            debug!("dv: {}", dv);
+        let me = id() & 0xffff;
+        let leader = self.wingmen.iter().filter(|w| w.id < me).min_by_key(|w| w.id);
+        let leader_id = leader.map(|l| l.id);
 
+        if leader_id != self.leader && self.telemetry.on(Category::Radio) {
+            debug!("new leader: {:?}", leader_id.unwrap_or(me));
         }
+        self.leader = leader_id;
+
+        let Some(l) = leader else {
+            self.formation = None;
+            return;
+        };
+
+        // Our place in a V behind the leader, by how many ships rank above us
+        let rank = self.wingmen.iter().filter(|w| w.id < me).count();
+        let row = rank.div_ceil(2) as f64;
+        let side = if rank % 2 == 1 { 1.0 } else { -1.0 };
+        let offset = vec2(-row, side * row) * self.config.formation_spacing;
+
+        // Where the leader is now, not when they reported
+        let leader_position = l.position + l.velocity * (current_time() - l.time);
+        self.formation = Some(leader_position + offset.rotate(l.heading));
+        self.formation_velocity = l.velocity;
+    }
 
-        if should_break {
-            accelerate(-1.0 * v_normalized * max_forward_acceleration());
-        } else {
-            accelerate(dv);
+    // Let the team know about every enemy we can see,
+    // so ships that can't see them can still engage.
+    // Only one report fits in a message, so we take turns, one enemy each time the last report goes out.
+    // Like position reports, ships send every few ticks, spread over different ticks.
+    fn share_targets(&mut self) {
+        if !(current_tick() + id()).is_multiple_of(self.config.target_report_period) {
+            return;
+        }
+        if self.radio.is_queued(self.config.team_channel, RadioMessage::TARGET_REPORT) {
+            return;
+        }
+
+        for i in 0..self.enemies.len() {
+            let n = (self.next_shared_enemy + i) % self.enemies.len();
+            let t = &self.enemies[n];
+            let hit = t.hits.last().unwrap();
+
+            // Don't echo back what our teammates told us
+            if !t.confirmed || hit.shared {
+                continue;
+            }
+
+            let report = RadioMessage::TargetReport {
+                class: hit.result.class,
+                position: hit.position,
+                velocity: hit.velocity,
+                time: hit.time,
+            };
+            self.radio.publish(self.config.team_channel, &report);
+            self.next_shared_enemy = n + 1;
+            return;
         }
     }
-    
-    fn update_weapon_readiness(&mut self, target: &TargetEstimate) {
-        let d = (position() - target.position).length();
-        let angle = (target.position - position()).angle();
-        let angle_diff = angle_diff(
-            heading(), 
-            angle
-        );
 
-        let is_reloading = reload_ticks(0) > 0;
-        let in_range = d < self.max_range;
-        let in_firing_arc = angle_diff.abs() <= target.angle_error;
+    fn handle_radio(&mut self, packet: RadioPacket) {
+        if self.telemetry.on(Category::Radio) { debug!("message from {}", packet.sender); }
 
-        if self.debug_fire {
-            debug!("is reloading: {is_reloading}");
-            debug!("in range: {in_range}; distance: {}; max range: {};", d, self.max_range);
-            debug!("in arc: {in_firing_arc}; angle dif: {}; angle error: {};", radian_to_degree(angle_diff), radian_to_degree(target.angle_error));
-            draw_line(position(), position() + vec2(self.max_range, 0.0).rotate(angle + target.angle_error), 0xed85dc);
-            draw_line(position(), position() + vec2(self.max_range, 0.0).rotate(angle - target.angle_error), 0xed85dc);
+        // Ignore our own broadcasts
+        if packet.sender == id() & 0xffff {
+            return;
         }
 
-        self.is_weapon_ready = !is_reloading && in_range && in_firing_arc;
+        match packet.message {
+            RadioMessage::TargetReport { class, position, velocity, time } => {
+                // Fuse it with our own targets, as if our radar had seen it when they did
+                let s = ScanResult { position, velocity, rssi: 0.0, snr: 0.0, class };
+                let mut hit = ScanHit::new(s, self.config.tracker.report_noise, time);
+                hit.shared = true;
+                self.track(hit);
+            }
+            RadioMessage::PositionReport { position, velocity, heading, time } => {
+                let wingman = Wingman { id: packet.sender, position, velocity, heading, time };
+                match self.wingmen.iter_mut().find(|w| w.id == packet.sender) {
+                    Some(w) if w.time < time => *w = wingman,
+                    Some(_) => {}
+                    None => self.wingmen.push(wingman),
+                }
+            }
+            RadioMessage::AttackOrder { recipient, position, velocity, time } if recipient == id() & 0xffff => {
+                let s = ScanResult { position, velocity, rssi: 0.0, snr: 0.0, class: Class::Unknown };
+                self.radio_target(s, time);
+            }
+            _ => {}
+        }
     }
-    
-    pub fn tick(&mut self) {
-        // self.scan();
-        self.radio();
 
-        // Need to stretch out the max(1m) number of instructions
-        if !self.skip_tick() {
-            if let Some(t) = self.closest_target.as_ref() {
-                draw_line(position(), t.position(), 0x00ff00);
-        
-                let target_in_time =  self.calc_future_target(100);
-                self.update_weapon_readiness(&target_in_time);
-    
-                draw_line(position(), target_in_time.position, 0x9c2488);
-        
-                self.turn(&target_in_time);
-                self.fire(&target_in_time);
-                self.move_ship(&target_in_time);
+    // Our orders come from the fleet's leader, so they replace what we are tracking
+    // Radio reports don't have a signal strength, so rssi and snr are left at 0
+    fn radio_target(&mut self, s: ScanResult, time: f64) {
+        // Radio reports are exact, so they have no measurement noise
+        let hit = ScanHit::new(s, 0.0, time);
+
+        if self.closest_target.is_none() {
+            self.closest_target = Some(Target::new(hit, &self.config.tracker));
+        } else {
+            let t = self.closest_target.as_mut().unwrap();
+            t.add_hit(hit, &self.config.tracker);
+        }
+    }
+
+    // The radar is read and steered every tick, so no returns are missed.
+    // Matching them to targets is the expensive part, so with `associate` false
+    // the hits wait in `pending_hits` until the scheduler next allows it.
+    fn scan(&mut self, associate: bool) {
+        if self.telemetry.on(Category::Scan) { 
+            debug!("Number of targets: {}", self.number_targets);
+        }
+
+        // Process scan
+        if let Some(s) = scan() {
+            if s.snr < self.config.tracker.min_snr {
+                // Too weak to tell apart from noise
+                if self.telemetry.on(Category::Scan) { debug!("ignoring contact; snr: {}", s.snr); }
+            } else {
+                let noise = self.config.tracker.noise(s.snr);
+                self.pending_hits.push(ScanHit::new(s, noise, current_time()));
+                if self.pending_hits.len() > 10 {
+                    self.pending_hits.remove(0);
+                }
+            }
+        }
+
+        if associate {
+            for hit in std::mem::take(&mut self.pending_hits) {
+                self.track(hit);
+            }
+        }
+
+        // Move rader
+        if self.search {
+            if radar_heading() < degree_to_radian(10.0) && self.closest_target.is_some() {
+                self.search = false;
+            } else {
+                self.search_start = self.search_start + degree_to_radian(10.0);
+                set_radar_heading(self.search_start);
+            }
+        } else if self.closest_target.is_some() {
+            let fp = self.closest_target.as_ref()
+                .unwrap()
+                .future_position(current_time() + TICK_LENGTH, false);
+
+            set_radar_heading((fp - position()).angle());
+
+            if position().distance(fp) < 100.0 {
+                set_radar_width(degree_to_radian(120.0));
             } else {
-                let a = velocity() * max_forward_acceleration() * -1.0;
-                accelerate(a);
+                set_radar_width(degree_to_radian(30.0));
             }
         } else {
-            debug!("SKIP TICK");
+            self.search = true;
+            self.search_start = 10.0;
+            set_radar_heading(self.search_start);
+            set_radar_width(degree_to_radian(10.0));
         }
     }
-}
 
-fn calculate_angles(a: Vec2, b: Vec2, c: Vec2) -> (f64, f64, f64) {
-    fn calc_first_angle(a: Vec2, b: Vec2, c: Vec2) -> f64 {
-        let B = b - a;
-        let C = c - a;
-        (B.dot(C) / (B.length() * C.length())).acos()
+    // Targets fed only by radio expire too, so this runs whether or not the radar is used
+    fn expire_targets(&mut self) {
+        if self.closest_target.as_ref().is_some() && self.closest_target.as_ref().unwrap().has_expired() {
+            self.closest_target = None;
+        }
+        if self.tentative_target.as_ref().is_some() && self.tentative_target.as_ref().unwrap().has_expired() {
+            self.tentative_target = None;
+        }
+        self.threats.retain(|t| !t.has_expired());
+        self.enemies.retain(|t| !t.has_expired());
+    }
+
+    fn track(&mut self, hit: ScanHit) {
+        // Missiles and torpedoes aren't worth chasing, but may need shooting down
+        if matches!(hit.result.class, Class::Missile | Class::Torpedo) {
+            self.track_threat(hit);
+            return;
+        }
+
+        self.track_enemy(&hit);
+
+        // Update the target we are already tracking
+        if let Some(t) = self.closest_target.as_mut() {
+            if t.match_last_seen(&hit, self.telemetry.on(Category::Scan)) {
+                t.add_hit(hit, &self.config.tracker);
+                return;
+            }
+        }
+
+        // New contacts start out as tentative, so a single false hit can't pull us off our target
+        if let Some(t) = self.tentative_target.as_mut() {
+            if t.match_last_seen(&hit, self.telemetry.on(Category::Scan)) {
+                t.add_hit(hit, &self.config.tracker);
+            } else if hit.position.distance(position()) < t.position().distance(position()) {
+                self.tentative_target = Some(Target::new(hit, &self.config.tracker));
+            }
+        } else {
+            self.tentative_target = Some(Target::new(hit, &self.config.tracker));
+        }
+
+        // Switch to the new contact once confirmed, if it is closer
+        let tentative = self.tentative_target.as_ref().unwrap();
+        if self.telemetry.on(Category::Scan) {
+            debug!("tentative hits: {}; confirmed: {}", tentative.hits.len(), tentative.confirmed);
+        }
+        if tentative.confirmed {
+            let is_closer = self.closest_target.as_ref()
+                .is_none_or(|t| tentative.position().distance(position()) < t.position().distance(position()));
+
+            // The fleet picked our target, so stick with it
+            let assigned = self.assigned && self.closest_target.is_some();
+
+            if is_closer && !assigned {
+                self.number_targets += 1;
+                self.closest_target = self.tentative_target.take();
+            }
+        }
+    }
+
+    // Every enemy ship we or the team know about, for sharing out targets
+    fn track_enemy(&mut self, hit: &ScanHit) {
+        if let Some(t) = self.enemies.iter_mut().find(|t| t.match_last_seen(hit, false)) {
+            t.add_hit(hit.clone(), &self.config.tracker);
+        } else if self.enemies.len() < 8 {
+            self.enemies.push(Target::new(hit.clone(), &self.config.tracker));
+        }
+    }
+
+    fn track_threat(&mut self, hit: ScanHit) {
+        if let Some(t) = self.threats.iter_mut().find(|t| t.match_last_seen(&hit, false)) {
+            t.add_hit(hit, &self.config.tracker);
+        } else if self.threats.len() < 8 {
+            self.threats.push(Target::new(hit, &self.config.tracker));
+        }
+    }
+
+    // Threats that will pass close enough to hit us, soonest first
+    fn incoming_threats(&self) -> Vec<(f64, &Target)> {
+        let mut incoming: Vec<(f64, &Target)> = self
+            .threats
+            .iter()
+            .filter_map(|t| {
+                let r = t.future_position(current_time(), true) - position();
+                let v = t.velocity() - velocity();
+                if r.dot(v) >= 0.0 {
+                    return None; // moving away
+                }
+
+                let time_to_impact = -r.dot(v) / v.dot(v);
+                let miss_distance = (r + v * time_to_impact).length();
+                (miss_distance < self.config.point_defense_radius).then_some((time_to_impact, t))
+            })
+            .collect();
+        incoming.sort_by(|a, b| a.0.total_cmp(&b.0));
+        incoming
+    }
+
+    fn calc_future_target(&self, target: &Target, depth_of_calc: u32, bullet_speed: f64) -> TargetEstimate {
+        // Hint: p = p₀ + v₀t + ½at² (the third equation of kinematics)
+        
+        // The target may have been measured a while ago (e.g. a late radio report),
+        // so we need to predict from when it was measured, not from now
+        let age = target.age();
+
+        let mut t = (target.position() - position()).length() / bullet_speed;
+        let mut pf = estimate_future_position(target.position(), target.velocity(), vec2(0.0,0.0), age + t);
+
+        // TODO: maybe just have some percent offset for t, instead of recalculating
+        for _ in [..depth_of_calc] {
+            t = (pf - position()).length() / bullet_speed;
+            pf = estimate_future_position(target.position(), target.velocity(), target.aceleration(), age + t);
+        }
+
+        let ninety_deg_angle = safe_normalize((position() - target.position()).rotate(degree_to_radian(90.0)));
+        let offset_a = ninety_deg_angle * max_forward_acceleration();
+        let pf_offset = estimate_future_position(target.position(), target.velocity(), offset_a, age + t);
+        let pf_offset2 = estimate_future_position(target.position(), target.velocity(), -1.0 * offset_a, age + t);
+        
+        // let pf = vec2((pf_offset.x + pf_offset2.x) / 2.0, (pf_offset.y + pf_offset2.y) / 2.0);
+
+        let m = position();
+        let blue = m - pf;
+        let red = m - pf_offset;
+        let gray = pf - pf_offset;
+        
+        // yellow green angle
+        let Y = pf_offset - target.position();
+        let G = position() - target.position();
+        let yg = angle_between(Y, G);
+
+        let angles = calculate_angles(m, pf, pf_offset);
+
+        let deg_blue_gray = radian_to_degree(angles.1);
+        let deb_blue_red = radian_to_degree(angles.0);
+        let deb_red_gray = radian_to_degree(angles.2);
+        let deg_total = deg_blue_gray + deb_blue_red + deb_red_gray;
+        
+        let angles2 = calculate_angles(m, pf, pf_offset2);
+        
+        let angle_error = angles.0.max(angles2.0).max(self.config.firing_offset);
+
+
+        if self.telemetry.on(Category::Intercept) {
+            debug!("(blue) m - pf = {}", blue.length());
+            debug!("(red)  m - pfo = {}", red.length());
+            debug!("(gray) pf - pfo = {}", gray.length());
+            debug!("deg_blue_red = {deb_blue_red}");
+            debug!("deg_blue_gray = {deg_blue_gray}");
+            debug!("deg_red_gray = {deb_red_gray}");
+            debug!("deg_total = {deg_total}");
+            debug!("angle_error = {}", radian_to_degree(angle_error));
+
+            
+            draw_line(m, pf, BLUE);
+            draw_line(m, pf_offset, DARK_RED);
+            draw_line(pf, pf_offset, GRAY);
+
+            draw_line(pf, pf_offset2, GRAY);
+            draw_line(m, pf_offset2, DARK_RED);
+
+
+            debug!("deg yellow green = {}", radian_to_degree(yg));
+            draw_line(target.position(), pf, PINK);
+            draw_line(target.position(), pf_offset, YELLOW);
+            draw_line(target.position(), target.position() + (offset_a * 1000.0), ORANGE);
+        }
+        self.telemetry.draw_envelope(pf, 0.5 * max_forward_acceleration() * t * t);
+        
+        TargetEstimate {
+            estimate_time: current_time() + t,
+            created_time: current_time(), 
+            position: pf,
+            angle_error,
+        }
+    }
+
+    fn get_fire_offset(&mut self, angle_error: f64) -> f64 {
+        let offset = angle_error * self.fire_offset_percent;
+        
+        // Update offset if we can fire this tick
+        if self.weapons.iter().any(|w| w.ready && w.weapon.mount == Mount::Fixed) {
+            self.fire_offset_percent = self.fire_offset_percent + self.fire_offset_percent_increment;
+            if self.fire_offset_percent >= 1.0 || self.fire_offset_percent <= -1.0 {
+                self.fire_offset_percent_increment = -1.0 * self.fire_offset_percent_increment;
+            }
+        }
+        
+        offset
+    }
+    
+    fn turn(&mut self, target: &TargetEstimate) {
+        // rand offset, to help account for them changing acceleration
+        let jitter = self.get_fire_offset(target.angle_error);
+
+        let target_angle = (target.position - position()).angle();
+        let target_angle_with_jitter = target_angle + jitter;
+        let angle_diff = angle_diff(
+            heading(), 
+            target_angle_with_jitter
+        );
+
+        let v = angular_velocity();
+        let seconds_to_stop = v.abs() / max_angular_acceleration();
+        // Not turning at all, so we never get there
+        let seconds_to_target = if v != 0.0 { angle_diff / v } else { f64::INFINITY };
+
+        let should_break = 
+            seconds_to_stop > 0.0 
+            && seconds_to_target > 0.0
+            && seconds_to_stop >= seconds_to_target;
+
+        if self.telemetry.on(Category::Turn) {
+                
+            debug!("target: {}; heading: {};", radian_to_degree(target_angle), radian_to_degree(heading()));
+            debug!("jitter: {}; diff: {};", radian_to_degree(jitter), radian_to_degree(angle_diff));
+            debug!("v: {}; max a: {};", radian_to_degree(v), radian_to_degree(max_angular_acceleration()));
+            debug!("to stop: {}; to target: {}; break: {}", seconds_to_stop, seconds_to_target, should_break);
+        }
+
+        if should_break {
+            // break!
+            if v > 0.0 {
+                torque(-1.0 * max_angular_acceleration());
+            } else {
+                torque(max_angular_acceleration());
+            }
+        } else {
+            let a = max_angular_acceleration();
+            if seconds_to_target < self.config.seconds_before_using_turn {
+                turn(target_angle - heading());
+            }
+
+            if angle_diff > 0.0 {
+                torque(a);
+            } else {
+                torque(-1.0 * a);
+            }
+        }
+    }
+    
+    fn fire(&self, _target: &TargetEstimate) {
+        for w in &self.weapons {
+            let on_target = w.weapon.mount == Mount::Fixed || w.on_solution;
+            if w.ready && on_target {
+                fire(w.weapon.index);
+            }
+        }
+    }
+
+    // Launch when the target is within reach of the missile, isn't already
+    // being chased by enough of our missiles, and is roughly where we point
+    fn launch_missiles(&mut self) {
+        let Some(t) = self.closest_target.as_ref() else {
+            return;
+        };
+        if !t.confirmed {
+            return;
+        }
+
+        // Forget missiles that have given up, or were chasing an old target
+        if self.missile_target != self.number_targets {
+            self.missiles_in_flight.clear();
+            self.missile_target = self.number_targets;
+        }
+        self.missiles_in_flight.retain(|&until| until > current_time());
+
+        let r = t.future_position(current_time(), true) - position();
+        let v = t.velocity() - velocity(); // a missile starts with our velocity
+        let in_arc = signed_angle(vec2(1.0, 0.0).rotate(heading()), r).abs() <= self.config.launch_arc;
+
+        for launcher in self.config.launchers.iter() {
+            if self.missiles_in_flight.len() >= self.config.max_missiles_per_target {
+                break;
+            }
+            if !in_arc || reload_ticks(launcher.index) > 0 {
+                continue;
+            }
+
+            // A torpedo is too slow to catch a fighter, so save it for bigger ships
+            if launcher.class == Class::Torpedo && t.class() == Class::Fighter {
+                continue;
+            }
+
+            // Can the missile catch the target before it runs out of time?
+            // It covers ½at² while the target moves on, so check a few times along its flight
+            let reach_time = (1..=10)
+                .map(|i| launcher.flight_time * i as f64 / 10.0)
+                .find(|&time| (r + v * time).length() <= 0.5 * launcher.acceleration * time * time);
+
+            if self.telemetry.on(Category::Fire) {
+                debug!("launcher {}: reach time: {:?}; in flight: {}", launcher.index, reach_time, self.missiles_in_flight.len());
+            }
+
+            if reach_time.is_some() {
+                fire(launcher.index);
+                self.missiles_in_flight.push(current_time() + launcher.flight_time);
+            }
+        }
+    }
+
+    // Points each turret at its own target, independent of where the ship points.
+    // A turret only turns `turn_rate` a second, so it's on solution once it has caught up.
+    fn aim_turrets(&mut self) {
+        for w in self.weapons.iter_mut() {
+            if w.weapon.mount != Mount::Turret {
+                continue;
+            }
+
+            if let Some(estimate) = w.estimate {
+                w.aim_heading = (estimate.position - position()).angle();
+                aim(w.weapon.index, w.aim_heading);
+            }
+
+            // Turn our copy of the turret towards where it was told to point
+            let wanted = angle_diff(heading(), w.aim_heading);
+            let step = w.weapon.turn_rate * TICK_LENGTH;
+            w.turret_angle = normalize_angle(w.turret_angle + angle_diff(w.turret_angle, wanted).clamp(-step, step));
+
+            w.on_solution = match w.estimate {
+                Some(estimate) => angle_diff(w.turret_angle, wanted).abs() <= estimate.angle_error,
+                None => false,
+            };
+
+            if self.telemetry.on(Category::Aim) {
+                let pointing = heading() + w.turret_angle;
+                draw_line(position(), position() + vec2(500.0, 0.0).rotate(pointing), if w.on_solution { PURPLE } else { GRAY });
+            }
+        }
+    }
+
+    fn move_ship(&self, target: &TargetEstimate) {
+        let dp = target.position - position();
+        
+        let heading_normilized = vec2(1.0, 0.0).rotate(heading());
+        let v = velocity();
+        let v_normalized = safe_normalize(velocity());
+        let target_v_normalized = safe_normalize(dp);
+        let v_normalized_diff = target_v_normalized - v_normalized;
+        
+        let dv =
+        if v.length() > 0.0 { (heading_normilized + v_normalized_diff) * max_forward_acceleration() }
+        else { heading_normilized * max_forward_acceleration() };
+
+        let seconds_to_accelerate_to_passing_speed = self.config.passing_speed / max_forward_acceleration();
+        let seconds_to_stop = v.length() / max_forward_acceleration();
+        let seconds_to_passing_speed = seconds_to_stop - seconds_to_accelerate_to_passing_speed;
+        // Stopped, so we never get there
+        let seconds_to_intercept = if v.length() > 0.0 { dp.length() / v.length() } else { f64::INFINITY };
+        let should_break = seconds_to_passing_speed >= seconds_to_intercept;
+        
+        if self.telemetry.on(Category::Move) {
+            debug!("should break: {}", should_break);
+            debug!("v: {}", velocity());
+            debug!("dv: {}", dv);
+
+        }
+
+        if should_break {
+            accelerate(-1.0 * v_normalized * max_forward_acceleration());
+        } else {
+            accelerate(dv);
+        }
+    }
+    
+    fn update_weapon_readiness(&mut self) {
+        for w in self.weapons.iter_mut() {
+            let Some(target) = w.estimate else {
+                w.ready = false;
+                continue;
+            };
+            let d = (position() - target.position).length();
+            let angle = (target.position - position()).angle();
+            let angle_diff = angle_diff(
+                heading(), 
+                angle
+            );
+
+            let is_reloading = reload_ticks(w.weapon.index) > 0;
+            let range = w.weapon.range.min(self.config.max_range);
+            let in_range = d < range;
+            // Turrets are pointed at the target on their own
+            let in_firing_arc = w.weapon.mount == Mount::Turret || angle_diff.abs() <= target.angle_error;
+
+            if self.telemetry.on(Category::Fire) {
+                debug!("weapon {}", w.weapon.index);
+                debug!("is reloading: {is_reloading}");
+                debug!("in range: {in_range}; distance: {}; max range: {};", d, range);
+                debug!("in arc: {in_firing_arc}; angle dif: {}; angle error: {};", radian_to_degree(angle_diff), radian_to_degree(target.angle_error));
+            }
+            if w.weapon.mount == Mount::Fixed {
+                self.telemetry.draw_firing_arc(angle, target.angle_error, range);
+            }
+
+            w.ready = !is_reloading && in_range && in_firing_arc;
+        }
+    }
+
+    // Bullet speed of the gun we aim by turning the ship
+    fn primary_bullet_speed(&self) -> f64 {
+        let fixed = self.weapons.iter().find(|w| w.weapon.mount == Mount::Fixed);
+        fixed
+            .or(self.weapons.first())
+            .map_or(BULLET_SPEED, |w| w.weapon.bullet_speed)
+    }
+
+    // Each weapon needs its own lead, since bullets fly at different speeds
+    fn update_weapon_estimates(&mut self) {
+        let primary_speed = self.primary_bullet_speed();
+        for i in 0..self.weapons.len() {
+            let speed = self.weapons[i].weapon.bullet_speed;
+            self.weapons[i].estimate = if speed == primary_speed {
+                self.target_estimate
+            } else {
+                Some(self.calc_future_target(self.closest_target.as_ref().unwrap(), 100, speed))
+            };
+        }
+    }
+    
+    pub fn tick(&mut self) {
+        if self.telemetry.on(Category::Record) {
+            debug!("{}", record_inputs(self.radio.radios));
+        }
+
+        if is_missile() {
+            self.guide_missile();
+            self.radio.transmit();
+            return;
+        }
+
+        let cruising = self.is_cruising();
+        self.scheduler.update(cruising);
+
+        if self.config.use_radar {
+            let associate = self.scheduler.should_run(Task::Association, false);
+            self.scan(associate);
+        }
+        self.radio();
+        self.expire_targets();
+        self.aim_turrets();
+
+        if let Some(tree) = self.tree.take() {
+            let mut budget = self.config.tree_max_nodes;
+            self.run_node(&tree, &mut budget);
+            self.tree = Some(tree);
+        } else {
+            let next = self.next_behavior();
+            if next != self.behavior {
+                self.exit_behavior(self.behavior);
+                self.enter_behavior(next);
+            }
+            self.run_behavior();
+        }
+
+        if self.telemetry.on(Category::Trace) {
+            debug!("{}", self.trace());
+        }
+        self.last_health = health();
+
+        self.radio.transmit();
+    }
+
+    fn next_behavior(&self) -> Behavior {
+        let threatened = self
+            .incoming_threats()
+            .first()
+            .is_some_and(|(time_to_impact, _)| *time_to_impact < self.config.point_defense_time);
+        if threatened {
+            return Behavior::PointDefense;
+        }
+
+        let Some(t) = self.closest_target.as_ref() else {
+            return match self.formation {
+                Some(_) => Behavior::ReturnToFormation,
+                None => Behavior::Search,
+            };
+        };
+
+        if health() < self.config.evade_health * self.starting_health {
+            return Behavior::Evade;
+        }
+
+        // Once engaged, only go back to approaching well outside our range,
+        // so we don't switch back and forth right at the edge
+        let d = t.position().distance(position());
+        match self.behavior {
+            Behavior::Engage if d < self.config.max_range * 1.3 => Behavior::Engage,
+            _ if d < self.config.max_range => Behavior::Engage,
+            _ => Behavior::Approach,
+        }
+    }
+
+    fn enter_behavior(&mut self, behavior: Behavior) {
+        if self.telemetry.on(Category::Behavior) {
+            debug!("behavior: {:?} -> {:?}", self.behavior, behavior);
+        }
+
+        match behavior {
+            Behavior::Search | Behavior::ReturnToFormation | Behavior::Evade | Behavior::PointDefense => {
+                self.target_estimate = None;
+            }
+            Behavior::Engage => {
+                // Start sweeping the firing arc from the middle again
+                self.fire_offset_percent = 0.0;
+            }
+            Behavior::Approach => {}
+        }
+
+        self.behavior = behavior;
+        self.behavior_start = current_time();
+    }
+
+    fn exit_behavior(&mut self, behavior: Behavior) {
+        if self.telemetry.on(Category::Behavior) {
+            debug!("behavior: {:?} lasted {:.1}s", behavior, current_time() - self.behavior_start);
+        }
+
+        if behavior == Behavior::Engage {
+            // Don't fire on an old solution if we come back
+            for w in self.weapons.iter_mut() {
+                w.ready = false;
+            }
+        }
+        if behavior == Behavior::PointDefense {
+            self.forget_threat_solutions();
+        }
+    }
+
+    // The solutions from point defense were for a threat, not the target
+    fn forget_threat_solutions(&mut self) {
+        if !self.threat_solutions {
+            return;
+        }
+        self.threat_solutions = false;
+        self.target_estimate = None;
+        for w in self.weapons.iter_mut() {
+            w.estimate = None;
+            w.ready = false;
+        }
+    }
+
+    fn run_behavior(&mut self) {
+        match self.behavior {
+            Behavior::Search => self.brake(),
+            Behavior::ReturnToFormation => {
+                if let Some(p) = self.formation {
+                    self.fly_to(p, self.formation_velocity);
+                }
+            }
+            Behavior::Approach | Behavior::Engage => {
+                let Some(target_in_time) = self.update_intercept() else {
+                    return;
+                };
+
+                self.turn(&target_in_time);
+                if self.behavior == Behavior::Engage {
+                    self.fire(&target_in_time);
+                }
+                self.launch_missiles();
+                self.move_ship(&target_in_time);
+            }
+            Behavior::Evade => self.evade(),
+            Behavior::PointDefense => self.point_defense(),
+        }
+    }
+
+    // Shoot at incoming missiles and torpedoes, most urgent first.
+    // Fixed guns take the most urgent one, and turrets share out the rest in order.
+    fn point_defense(&mut self) {
+        let (target_in_time, estimates) = {
+            let incoming = self.incoming_threats();
+            let Some((_, first)) = incoming.first() else {
+                return;
+            };
+
+            let mut next_turret_threat = 0;
+            let estimates: Vec<TargetEstimate> = self
+                .weapons
+                .iter()
+                .map(|w| {
+                    let threat = match w.weapon.mount {
+                        Mount::Fixed => first,
+                        Mount::Turret => {
+                            let (_, t) = &incoming[next_turret_threat % incoming.len()];
+                            next_turret_threat += 1;
+                            t
+                        }
+                    };
+                    self.calc_future_target(threat, 100, w.weapon.bullet_speed)
+                })
+                .collect();
+
+            if self.telemetry.on(Category::Fire) {
+                debug!("point defense: {} incoming", incoming.len());
+            }
+            self.telemetry.draw_track(first);
+
+            (self.calc_future_target(first, 100, self.primary_bullet_speed()), estimates)
+        };
+
+        self.target_estimate = Some(target_in_time);
+        for (w, estimate) in self.weapons.iter_mut().zip(estimates) {
+            w.estimate = Some(estimate);
+        }
+        self.threat_solutions = true;
+        self.update_weapon_readiness();
+        self.telemetry.draw_aim_point(target_in_time.position);
+
+        self.turn(&target_in_time);
+        self.fire(&target_in_time);
+    }
+
+    // Where to aim at the target, recalculated when the scheduler allows
+    fn update_intercept(&mut self) -> Option<TargetEstimate> {
+        let t = self.closest_target.as_ref()?;
+        self.telemetry.draw_track(t);
+
+        if self.scheduler.should_run(Task::Intercept, self.target_estimate.is_none()) {
+            self.target_estimate = Some(self.calc_future_target(t, 100, self.primary_bullet_speed()));
+            self.update_weapon_estimates();
+        }
+        let target_in_time = self.target_estimate.unwrap();
+
+        if self.scheduler.should_run(Task::Envelope, false) {
+            self.update_weapon_readiness();
+        }
+
+        self.telemetry.draw_aim_point(target_in_time.position);
+        Some(target_in_time)
+    }
+
+    fn brake(&self) {
+        let a = velocity() * max_forward_acceleration() * -1.0;
+        accelerate(a);
+    }
+
+    fn evade(&self) {
+        let Some(t) = self.closest_target.as_ref() else {
+            return;
+        };
+        self.telemetry.draw_track(t);
+
+        // Run away from the target, switching sides every 2 seconds
+        // so our path is hard to lead
+        let away = safe_normalize(position() - t.position());
+        let side = if (current_time() / 2.0) as i64 % 2 == 0 { 1.0 } else { -1.0 };
+        accelerate((away + vec2(-away.y, away.x) * side) * max_forward_acceleration());
+    }
+
+    // Walks the tree, visiting at most `budget` nodes so a big tree can't use up our instructions.
+    // When the budget runs out the rest of the nodes report Running and don't run this tick.
+    // Every tick starts again from the root, so nodes past the budget never run at all;
+    // keep the things that must happen near the front of the tree.
+    fn run_node(&mut self, node: &Node, budget: &mut usize) -> TreeStatus {
+        if *budget == 0 {
+            return TreeStatus::Running;
+        }
+        *budget -= 1;
+
+        match node {
+            Node::Sequence(children) => {
+                for child in children {
+                    let status = self.run_node(child, budget);
+                    if status != TreeStatus::Success {
+                        return status;
+                    }
+                }
+                TreeStatus::Success
+            }
+            Node::Selector(children) => {
+                for child in children {
+                    let status = self.run_node(child, budget);
+                    if status != TreeStatus::Failure {
+                        return status;
+                    }
+                }
+                TreeStatus::Failure
+            }
+            Node::Invert(child) => match self.run_node(child, budget) {
+                TreeStatus::Success => TreeStatus::Failure,
+                TreeStatus::Failure => TreeStatus::Success,
+                TreeStatus::Running => TreeStatus::Running,
+            },
+            Node::Succeed(child) => match self.run_node(child, budget) {
+                TreeStatus::Running => TreeStatus::Running,
+                _ => TreeStatus::Success,
+            },
+            Node::Condition(condition) => {
+                if self.check(*condition) {
+                    TreeStatus::Success
+                } else {
+                    TreeStatus::Failure
+                }
+            }
+            Node::Action(action) => self.act(*action),
+        }
+    }
+
+    fn check(&self, condition: Condition) -> bool {
+        match condition {
+            Condition::ThreatIncoming => self
+                .incoming_threats()
+                .first()
+                .is_some_and(|(time_to_impact, _)| *time_to_impact < self.config.point_defense_time),
+            Condition::HasTarget => self.closest_target.is_some(),
+            Condition::TargetInRange => self
+                .closest_target
+                .as_ref()
+                .is_some_and(|t| t.position().distance(position()) < self.config.max_range),
+            Condition::Damaged => health() < self.config.evade_health * self.starting_health,
+            Condition::HasFormation => self.formation.is_some(),
+        }
+    }
+
+    fn act(&mut self, action: Action) -> TreeStatus {
+        // Turning, firing and moving need an intercept solution from `Track` first
+        let estimate = self.target_estimate;
+
+        match action {
+            Action::Track => {
+                // Point defense may have run earlier in the tree, and left its solutions behind
+                self.forget_threat_solutions();
+                if self.update_intercept().is_none() {
+                    self.target_estimate = None;
+                    return TreeStatus::Failure;
+                }
+            }
+            Action::Turn | Action::Fire | Action::Move => {
+                let Some(target_in_time) = estimate else {
+                    return TreeStatus::Failure;
+                };
+                match action {
+                    Action::Turn => self.turn(&target_in_time),
+                    Action::Fire => self.fire(&target_in_time),
+                    _ => self.move_ship(&target_in_time),
+                }
+            }
+            Action::Launch => self.launch_missiles(),
+            Action::PointDefense => self.point_defense(),
+            Action::Brake => self.brake(),
+            Action::Evade => self.evade(),
+            Action::ReturnToFormation => match self.formation {
+                Some(p) => self.fly_to(p, self.formation_velocity),
+                None => return TreeStatus::Failure,
+            },
+        }
+        TreeStatus::Success
+    }
+
+    // Fly to a point that moves with `point_velocity`, and stay on it
+    fn fly_to(&self, point: Vec2, point_velocity: Vec2) {
+        // Aim to catch up in about 2 seconds, no faster than our cruising speed
+        let mut wanted = (point - position()) / 2.0;
+        if wanted.length() > self.config.max_speed {
+            wanted = safe_normalize(wanted) * self.config.max_speed;
+        }
+        accelerate((point_velocity + wanted - velocity()) * 10.0);
+    }
+
+    // Proportional navigation (PN)
+    // Turn in proportion to how fast the line of sight (LOS) to the target rotates.
+    // If the LOS doesn't rotate we are on a collision course, so all that's left is to speed up.
+    // Augmented PN also steers for the target's acceleration, which helps against a maneuvering target.
+    fn guide_missile(&mut self) {
+        // The ship that launched us tells us over radio where the target is,
+        // and our own radar locks on once it sees it
+        self.scan(true);
+        self.radio();
+        self.expire_targets();
+
+        let Some(t) = self.closest_target.as_ref() else {
+            // Nothing to chase yet, keep flying straight
+            accelerate(vec2(max_forward_acceleration(), 0.0).rotate(heading()));
+            return;
+        };
+        self.telemetry.draw_track(t);
+
+        let r = t.future_position(current_time(), true) - position(); // to the target
+        let v = t.velocity() - velocity();                              // target velocity relative to us
+        let d = r.length();
+
+        // When will we be closest, and how close?
+        let t_closest = if v.length() > 0.0 { -r.dot(v) / v.dot(v) } else { 0.0 };
+        let miss_distance = (r + v * t_closest.max(0.0)).length();
+
+        // Explode at the closest point, since after that we only get further away
+        if t_closest < TICK_LENGTH && miss_distance < self.config.blast_radius {
+            if self.telemetry.on(Category::Fire) {
+                debug!("detonating; distance: {}; miss distance: {}", d, miss_distance);
+            }
+            explode();
+            return;
+        }
+        if d == 0.0 {
+            return;
+        }
+
+        let los = r / d;
+        let normal = vec2(-los.y, los.x);
+        let los_rate = (r.x * v.y - r.y * v.x) / (d * d); // radians/s
+        let closing_speed = -r.dot(v) / d;
+
+        let n = self.config.nav_constant;
+        let mut lateral = n * closing_speed * los_rate;
+        if self.config.augmented_nav {
+            lateral += n / 2.0 * t.aceleration().dot(normal);
+        }
+
+        // Whatever acceleration is left over goes towards the target
+        let max_a = max_forward_acceleration();
+        let lateral = lateral.clamp(-max_a, max_a);
+        let forward = (max_a * max_a - lateral * lateral).sqrt();
+        let a = los * forward + normal * lateral;
+
+        if self.telemetry.on(Category::Intercept) {
+            debug!("distance: {}; closing speed: {}; los rate: {}", d, closing_speed, los_rate);
+            debug!("time to closest: {}; miss distance: {}", t_closest, miss_distance);
+            draw_line(position(), position() + a, ORANGE);
+        }
+
+        // Point the way we accelerate, since missiles push hardest forwards
+        turn(angle_diff(heading(), a.angle()) * 10.0);
+        accelerate(a);
+    }
+
+    // What we did this tick, as a line of JSON
+    // e.g. the miss distance over time can be plotted from `aim` and the later `target` positions.
+    // `fire` lists every gun and launcher fired, and `damage` is the health lost since last tick,
+    // the closest we can get to seeing hits on us.
+    fn trace(&self) -> String {
+        let target = self.closest_target.as_ref().map(|t| t.position());
+        let aim = self.target_estimate.map(|e| e.position);
+
+        format!(
+            "{{\"tick\":{},\"position\":{},\"velocity\":{},\"heading\":{},\"torque\":{},\"target\":{},\"aim\":{},\"angle_error\":{},\"fire\":{:?},\"damage\":{}}}",
+            current_tick(),
+            json_vec2(Some(position())),
+            json_vec2(Some(velocity())),
+            json_f64(heading()),
+            json_f64(read_system_state(SystemState::Torque)),
+            json_vec2(target),
+            json_vec2(aim),
+            self.target_estimate.map_or("null".to_string(), |e| json_f64(e.angle_error)),
+            fired_slots(),
+            json_f64(self.last_health - health()),
+        )
+    }
+}
+
+// Weapon and launcher slots that were told to fire this tick
+fn fired_slots() -> Vec<usize> {
+    let slots = [SystemState::Fire0, SystemState::Fire1, SystemState::Fire2, SystemState::Fire3];
+    (0..slots.len()).filter(|i| read_system_state(slots[*i]) != 0.0).collect()
+}
+
+// JSON has no NaN or infinity
+fn json_f64(v: f64) -> String {
+    if v.is_finite() { v.to_string() } else { "null".to_string() }
+}
+
+fn json_vec2(v: Option<Vec2>) -> String {
+    match v {
+        Some(v) => format!("[{},{}]", json_f64(v.x), json_f64(v.y)),
+        None => "null".to_string(),
+    }
+}
+
+// Record and replay
+// With the Record category on, the ship writes the config it was made with, then each tick
+// a line with every input the ship can read (its own state, the radar contact and radio messages).
+// Copy those lines out of the debug output, and `tools::replay` will feed them back into a new `Ship`
+// to reproduce what it did, e.g. from a test.
+fn recorded_inputs(radios: usize) -> Vec<SystemState> {
+    let mut inputs = vec![
+        // Our own state
+        SystemState::Class,
+        SystemState::Id,
+        SystemState::Seed,
+        SystemState::CurrentTick,
+        SystemState::PositionX,
+        SystemState::PositionY,
+        SystemState::VelocityX,
+        SystemState::VelocityY,
+        SystemState::Heading,
+        SystemState::AngularVelocity,
+        SystemState::MaxForwardAcceleration,
+        SystemState::MaxBackwardAcceleration,
+        SystemState::MaxLateralAcceleration,
+        SystemState::MaxAngularAcceleration,
+        SystemState::ReloadTicks0,
+        SystemState::ReloadTicks1,
+        SystemState::ReloadTicks2,
+        SystemState::ReloadTicks3,
+        SystemState::Health,
+        SystemState::Fuel,
+    ];
+
+    // Radar contact
+    let radar = radar_internal::radar_contact_indices(0);
+    inputs.extend([
+        radar.found,
+        radar.class,
+        radar.position[0],
+        radar.position[1],
+        radar.velocity[0],
+        radar.velocity[1],
+        radar.rssi,
+        radar.snr,
+    ]);
+
+    // Radio messages
+    for r in 0..radios {
+        let radio = radio_internal::radio_indices(r);
+        inputs.push(radio.receive);
+        inputs.extend(radio.data);
+    }
+
+    inputs
+}
+
+// "cfg <name>=<value>...", so a replay uses the same config even if it was overridden,
+// e.g. by a sweep
+fn record_config(config: &ShipConfig) -> String {
+    let mut line = "cfg".to_string();
+    for name in ShipConfig::KEYS {
+        if let Some(value) = config.get(name) {
+            line.push_str(&format!(" {}={}", name, value));
+        }
+    }
+    line
+}
+
+// "rec <radios> <inputs...>"
+fn record_inputs(radios: usize) -> String {
+    let mut line = format!("rec {}", radios);
+    for input in recorded_inputs(radios) {
+        line.push_str(&format!(" {}", read_system_state(input)));
+    }
+    line
+}
+
+// Offline tools
+// Replays, SVG rendering, parameter sweeps and the league are in 9_radio_tools.rs.
+// The game builds for wasm32, so it never loads that file.
+#[cfg(not(target_arch = "wasm32"))]
+#[path = "9_radio_tools.rs"]
+pub mod tools;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Vec2, b: Vec2, tolerance: f64) -> bool {
+        a.distance(b) <= tolerance
+    }
+
+    // The game's state is one global array, so tests that use it take turns
+    static SYSTEM_STATE_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    // Locks the state and clears it, as if the ship had just spawned at rest
+    pub(crate) fn fresh_state() -> std::sync::MutexGuard<'static, ()> {
+        let guard = SYSTEM_STATE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        clear_state();
+        guard
+    }
+
+    // Only while holding the lock from `fresh_state`
+    pub(crate) fn clear_state() {
+        unsafe { *std::ptr::addr_of_mut!(oort_api::sys::SYSTEM_STATE) = [0; SystemState::MaxSize as usize] };
+    }
+
+    fn set_state(state: SystemState, value: f64) {
+        oort_api::sys::write_system_state(state, value);
+    }
+
+    fn radio_channel(radio: usize) -> usize {
+        read_system_state(radio_internal::radio_indices(radio).channel) as usize
+    }
+
+    // Math
+
+    fn close_angle(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn normalize_angle_wraps_to_plus_minus_pi() {
+        assert!(close_angle(normalize_angle(0.0), 0.0));
+        assert!(close_angle(normalize_angle(TAU + 0.5), 0.5));
+        assert!(close_angle(normalize_angle(-TAU - 0.5), -0.5));
+        assert!(close_angle(normalize_angle(PI + 0.5), 0.5 - PI));
+        assert!(close_angle(normalize_angle(-PI - 0.5), PI - 0.5));
+        for i in -100..100 {
+            let a = normalize_angle(i as f64 * 0.7);
+            assert!((-PI..=PI).contains(&a), "{} wrapped to {}", i as f64 * 0.7, a);
+        }
+    }
+
+    #[test]
+    fn safe_acos_clamps_out_of_range_cosines() {
+        assert_eq!(safe_acos(1.0 + 1e-12), 0.0);
+        assert!(close_angle(safe_acos(-1.0 - 1e-12), PI));
+        assert_eq!(safe_acos(5.0), 0.0);
+        assert!(close_angle(safe_acos(-5.0), PI));
+        assert!(close_angle(safe_acos(0.0), PI / 2.0));
+        assert_eq!(safe_acos(f64::NAN), 0.0);
+    }
+
+    #[test]
+    fn angle_between_is_zero_to_pi() {
+        assert!(close_angle(angle_between(vec2(1.0, 0.0), vec2(0.0, 2.0)), PI / 2.0));
+        assert!(close_angle(angle_between(vec2(1.0, 0.0), vec2(0.0, -2.0)), PI / 2.0));
+        assert!(close_angle(angle_between(vec2(1.0, 1.0), vec2(-3.0, -3.0)), PI));
+        assert_eq!(angle_between(vec2(3.0, 4.0), vec2(6.0, 8.0)), 0.0);
+
+        // No length, no angle
+        assert_eq!(angle_between(vec2(0.0, 0.0), vec2(1.0, 0.0)), 0.0);
+        assert_eq!(angle_between(vec2(1.0, 0.0), vec2(0.0, 0.0)), 0.0);
+        assert_eq!(angle_between(vec2(f64::INFINITY, 0.0), vec2(1.0, 0.0)), 0.0);
+    }
+
+    #[test]
+    fn signed_angle_is_positive_counterclockwise() {
+        assert!(close_angle(signed_angle(vec2(1.0, 0.0), vec2(0.0, 1.0)), PI / 2.0));
+        assert!(close_angle(signed_angle(vec2(1.0, 0.0), vec2(0.0, -1.0)), -PI / 2.0));
+        assert!(close_angle(signed_angle(vec2(0.0, 1.0), vec2(1.0, 0.0)), -PI / 2.0));
+        assert!(close_angle(signed_angle(vec2(1.0, 0.0), vec2(-1.0, 0.0)).abs(), PI));
+        assert_eq!(signed_angle(vec2(0.0, 0.0), vec2(0.0, 1.0)), 0.0);
+    }
+
+    #[test]
+    fn safe_normalize_gives_unit_or_zero() {
+        let v = safe_normalize(vec2(3.0, -4.0));
+        assert!(close(v, vec2(0.6, -0.8), 1e-12));
+        assert!(close_angle(safe_normalize(vec2(1e-100, 0.0)).length(), 1.0));
+        assert_eq!(safe_normalize(vec2(0.0, 0.0)), vec2(0.0, 0.0));
+        assert_eq!(safe_normalize(vec2(f64::INFINITY, 1.0)), vec2(0.0, 0.0));
+        assert_eq!(safe_normalize(vec2(f64::NAN, 1.0)), vec2(0.0, 0.0));
+    }
+
+    // At rest
+
+    fn commands_are_finite() -> bool {
+        [SystemState::AccelerateX, SystemState::AccelerateY, SystemState::Torque]
+            .iter()
+            .all(|c| read_system_state(*c).is_finite())
+    }
+
+    #[test]
+    fn turning_and_moving_from_rest_head_for_the_target() {
+        let _state = fresh_state();
+        set_state(SystemState::MaxForwardAcceleration, 60.0);
+        set_state(SystemState::MaxBackwardAcceleration, 30.0);
+        set_state(SystemState::MaxLateralAcceleration, 30.0);
+        set_state(SystemState::MaxAngularAcceleration, TAU);
+        let mut ship = fighter();
+
+        // Not moving or spinning, with the target ahead, behind, and right where we are
+        set_state(SystemState::VelocityX, 0.0);
+        set_state(SystemState::VelocityY, 0.0);
+        set_state(SystemState::AngularVelocity, 0.0);
+        for p in [vec2(1000.0, 0.0), vec2(-1000.0, 0.0), vec2(0.0, 0.0)] {
+            let target = TargetEstimate { estimate_time: 0.0, created_time: 0.0, position: p, angle_error: 0.0 };
+            ship.turn(&target);
+            assert!(commands_are_finite(), "turning to {:?}", p);
+            ship.move_ship(&target);
+            assert!(commands_are_finite(), "moving to {:?}", p);
+        }
+
+        // Pointing along +x, so a target ahead means thrusting forward
+        let ahead = TargetEstimate { estimate_time: 0.0, created_time: 0.0, position: vec2(1000.0, 0.0), angle_error: 0.0 };
+        ship.move_ship(&ahead);
+        assert!(read_system_state(SystemState::AccelerateX) > 0.0);
+
+        // and a target to our left or right means turning towards it
+        for (p, side) in [(vec2(0.0, 1000.0), 1.0), (vec2(0.0, -1000.0), -1.0)] {
+            let target = TargetEstimate { estimate_time: 0.0, created_time: 0.0, position: p, angle_error: 0.0 };
+            ship.turn(&target);
+            let torque = read_system_state(SystemState::Torque);
+            assert!(torque * side > 0.0, "torque {} turning to {:?}", torque, p);
+        }
+    }
+
+    #[test]
+    fn invalid_acceleration_is_replaced_by_zero() {
+        let _state = fresh_state();
+        set_state(SystemState::MaxForwardAcceleration, 60.0);
+        set_state(SystemState::MaxBackwardAcceleration, 30.0);
+        set_state(SystemState::MaxLateralAcceleration, 30.0);
+        set_state(SystemState::AccelerateX, 10.0);
+        set_state(SystemState::AccelerateY, 10.0);
+        accelerate(vec2(f64::NAN, 0.0));
+        assert_eq!(read_system_state(SystemState::AccelerateX), 0.0);
+        assert_eq!(read_system_state(SystemState::AccelerateY), 0.0);
+    }
+
+    #[test]
+    fn invalid_radar_heading_keeps_the_old_one() {
+        let _state = fresh_state();
+        set_radar_heading(1.0);
+        set_radar_heading(f64::INFINITY);
+        assert_eq!(radar_heading(), 1.0);
+    }
+
+    // Radio manager
+
+    #[test]
+    fn raw_channel_has_its_own_radio() {
+        let _state = fresh_state();
+        let mut radio = RadioManager::new(2);
+        radio.dedicate(2);
+        radio.subscribe(5);
+        radio.subscribe(6);
+
+        for tick in 0..10 {
+            set_state(SystemState::CurrentTick, tick as f64);
+            radio.transmit();
+            assert_eq!(radio_channel(0), 2);
+            assert_eq!(radio_channel(1), [5, 6][tick % 2]);
+        }
+    }
+
+    #[test]
+    fn one_radio_takes_turns() {
+        let _state = fresh_state();
+        let mut radio = RadioManager::new(1);
+        radio.dedicate(2);
+        radio.subscribe(5);
+
+        for tick in 0..4 {
+            set_state(SystemState::CurrentTick, tick as f64);
+            radio.transmit();
+            assert_eq!(radio_channel(0), [2, 5][tick % 2]);
+        }
+    }
+
+    // Sends on the team channel for `ticks` ticks, with a target to share every tick,
+    // and returns the kinds of message that were sent
+    fn team_messages(ship_id: u32, ticks: u32) -> Vec<u64> {
+        set_state(SystemState::Id, ship_id as f64);
+        let mut config = ShipConfig::for_class(Class::Fighter);
+        config.telemetry = 0;
+        config.radios = 1;
+        config.raw_radio_channel = Some(2); // so the team channel takes turns with it
+        let mut ship = Ship::with_config(config);
+
+        let mut hit = report_hit(vec2(1000.0, 0.0), vec2(0.0, 0.0), current_time());
+        hit.shared = false;
+        ship.track(hit);
+        ship.enemies[0].confirmed = true;
+
+        let send = radio_internal::radio_indices(0).send;
+        let mut sent = Vec::new();
+        for tick in 0..ticks {
+            set_state(SystemState::CurrentTick, tick as f64);
+            ship.radio();
+            ship.radio.transmit();
+            if read_system_state(send) != 0.0 {
+                let msg = radio_internal::radio_indices(0).data.map(read_system_state);
+                sent.push(RadioMessage::decode(msg).expect("decodes").message.kind());
+                set_state(send, 0.0);
+            }
+        }
+        sent
+    }
+
+    #[test]
+    fn position_reports_are_not_overwritten() {
+        for ship_id in [1, 2, 3, 4] {
+            let _state = fresh_state();
+            // Reports are due every 10 ticks, and get a few more ticks to go out
+            let sent = team_messages(ship_id, 105);
+            let reports = sent.iter().filter(|k| **k == RadioMessage::POSITION_REPORT).count();
+            assert_eq!(reports, 10, "ship {} sent {} position reports", ship_id, reports);
+            // Target reports are due every `target_report_period` ticks, and don't crowd them out either
+            let period = ShipConfig::for_class(Class::Fighter).target_report_period;
+            let targets = sent.iter().filter(|k| **k == RadioMessage::TARGET_REPORT).count();
+            assert!(targets >= (105 / period) as usize - 1, "ship {} only sent {} target reports", ship_id, targets);
+        }
+    }
+
+    // Raw channel
+
+    // Tells the ship it heard [x, y, vx, vy] on the raw channel, as the game does in the Radio tutorial
+    fn hear_raw(ship: &mut Ship, tick: u32, position: Vec2, velocity: Vec2) {
+        set_state(SystemState::CurrentTick, tick as f64);
+        ship.radio.transmit();
+        set_state(SystemState::CurrentTick, (tick + 1) as f64);
+        let radio = radio_internal::radio_indices(0);
+        set_state(radio.receive, 1.0);
+        for (i, v) in [position.x, position.y, velocity.x, velocity.y].into_iter().enumerate() {
+            set_state(radio.data[i], v);
+        }
+        ship.radio();
+        set_state(radio.receive, 0.0);
+    }
+
+    fn tutorial_ship() -> Ship {
+        let mut config = ShipConfig::for_class(Class::Fighter);
+        config.telemetry = 0;
+        config.raw_radio_channel = Some(2);
+        Ship::with_config(config)
+    }
+
+    #[test]
+    fn raw_channel_is_only_on_in_the_tutorial() {
+        let _state = fresh_state();
+        let mut config = ShipConfig::for_class(Class::Fighter);
+        assert!(config.use_radar);
+        assert_eq!(config.raw_radio_channel, None);
+        config.apply_toml(CONFIG_OVERRIDES, Class::Fighter);
+        assert_eq!(config.raw_radio_channel, Some(2));
+    }
+
+    #[test]
+    fn toml_sections_only_apply_to_their_class() {
+        let _state = fresh_state();
+        let toml = "max_range = 1000\n[frigate]\nmax_range = 2000\n[fighter]\nmax_speed = 300";
+        let mut fighter = ShipConfig::for_class(Class::Fighter);
+        fighter.apply_toml(toml, Class::Fighter);
+        assert_eq!(fighter.max_range, 1000.0);
+        assert_eq!(fighter.max_speed, 300.0);
+
+        let mut frigate = ShipConfig::for_class(Class::Frigate);
+        let max_speed = frigate.max_speed;
+        frigate.apply_toml(toml, Class::Frigate);
+        assert_eq!(frigate.max_range, 2000.0);
+        assert_eq!(frigate.max_speed, max_speed);
+    }
+
+    #[test]
+    fn toml_comments_and_bools_are_read() {
+        let _state = fresh_state();
+        let mut config = ShipConfig::for_class(Class::Fighter);
+        config.apply_toml(
+            "# a whole line comment\n\nuse_radar = false # off\nbehavior_tree = true\ndebug_scan = \"true\"",
+            Class::Fighter,
+        );
+        assert!(!config.use_radar);
+        assert!(config.behavior_tree);
+        assert!(config.telemetry & Category::Scan as u32 != 0);
+    }
+
+    #[test]
+    fn unknown_toml_key_changes_nothing() {
+        let _state = fresh_state();
+        let before = ShipConfig::for_class(Class::Fighter);
+        let mut config = ShipConfig::for_class(Class::Fighter);
+        config.apply_toml("no_such_setting = 5\nmax_range = oops\nmax_range", Class::Fighter);
+        for name in ShipConfig::KEYS {
+            assert_eq!(config.get(name), before.get(name), "{}", name);
+        }
+        assert!(!config.set("no_such_setting", 5.0));
+    }
+
+    #[test]
+    fn every_key_can_be_set() {
+        let _state = fresh_state();
+        let mut config = ShipConfig::for_class(Class::Fighter);
+        for name in ShipConfig::KEYS {
+            assert!(config.set(name, 1.0), "{} isn't accepted by set", name);
+            assert_eq!(config.get(name), Some(1.0), "{}", name);
+        }
+    }
+
+    #[test]
+    fn raw_reports_confirm_a_target() {
+        let _state = fresh_state();
+        let mut ship = tutorial_ship();
+        let velocity = vec2(50.0, 0.0);
+        for tick in 0..10 {
+            // Sent the tick before we hear it
+            let p = vec2(3000.0, 1000.0) + velocity * ((tick * 2) as f64 * TICK_LENGTH);
+            hear_raw(&mut ship, tick * 2, p, velocity);
+        }
+        let t = ship.closest_target.as_ref().expect("raw reports confirm the target");
+        assert!(close(t.position(), vec2(3000.0, 1000.0) + velocity * (18.0 * TICK_LENGTH), 1.0));
+    }
+
+    #[test]
+    fn foreign_raw_message_cannot_steer_our_target() {
+        let _state = fresh_state();
+        let mut ship = tutorial_ship();
+        ship.track(report_hit(vec2(1000.0, 0.0), vec2(0.0, 0.0), 0.0));
+        assert!(ship.closest_target.is_some());
+
+        // Someone else's floats on channel 2, far from our target
+        hear_raw(&mut ship, 5, vec2(-5000.0, 3000.0), vec2(0.0, 0.0));
+        let t = ship.closest_target.as_ref().unwrap();
+        assert!(close(t.position(), vec2(1000.0, 0.0), 1.0));
+        assert_eq!(t.hits.len(), 1);
+    }
+
+    // Radar
+
+    fn radar_contact(position: Vec2, snr: f64) {
+        let radar = radar_internal::radar_contact_indices(0);
+        set_state(radar.found, 1.0);
+        set_state(radar.position[0], position.x);
+        set_state(radar.position[1], position.y);
+        set_state(radar.snr, snr);
+    }
+
+    #[test]
+    fn radar_is_steered_and_read_between_associations() {
+        let _state = fresh_state();
+        let mut ship = fighter();
+        radar_contact(vec2(1000.0, 0.0), 30.0);
+
+        for tick in 0..4 {
+            set_state(SystemState::CurrentTick, tick as f64);
+            set_state(SystemState::Radar0Heading, -1.0);
+            ship.scan(false);
+            assert!(read_system_state(SystemState::Radar0Heading) != -1.0, "radar steered on tick {}", tick);
+        }
+        assert_eq!(ship.pending_hits.len(), 4);
+        assert!(ship.closest_target.is_none());
+
+        ship.scan(true);
+        assert!(ship.pending_hits.is_empty());
+        assert!(ship.closest_target.is_some());
+    }
+
+    // Tracker
+
+    // A hit from our own radar with the given snr, as `scan` would make it
+    fn radar_hit(ship: &Ship, position: Vec2, snr: f64, time: f64) -> ScanHit {
+        let s = ScanResult { position, velocity: vec2(0.0, 0.0), rssi: 0.0, snr, class: Class::Fighter };
+        ScanHit::new(s, ship.config.tracker.noise(snr), time)
+    }
+
+    #[test]
+    fn hit_below_min_snr_is_ignored() {
+        let _state = fresh_state();
+        let mut ship = fighter();
+        radar_contact(vec2(1000.0, 0.0), ship.config.tracker.min_snr - 0.5);
+        ship.scan(true);
+        assert!(ship.pending_hits.is_empty());
+        assert!(ship.closest_target.is_none() && ship.tentative_target.is_none());
+
+        radar_contact(vec2(1000.0, 0.0), ship.config.tracker.min_snr + 0.5);
+        ship.scan(true);
+        assert!(ship.tentative_target.is_some());
+    }
+
+    #[test]
+    fn weak_hits_stay_tentative_until_confirm_hits() {
+        let _state = fresh_state();
+        let mut ship = fighter();
+        let snr = ship.config.tracker.min_snr + 1.0;
+        let confirm_hits = ship.config.tracker.confirm_hits;
+
+        for i in 0..confirm_hits {
+            assert!(ship.closest_target.is_none(), "confirmed after {} hits", i);
+            let hit = radar_hit(&ship, vec2(1000.0, 0.0), snr, i as f64 * TICK_LENGTH);
+            ship.track(hit);
+        }
+        assert!(ship.closest_target.as_ref().is_some_and(|t| t.confirmed));
+        assert_eq!(ship.closest_target.as_ref().unwrap().hits.len(), confirm_hits);
+    }
+
+    #[test]
+    fn strong_hit_confirms_at_once() {
+        let _state = fresh_state();
+        let mut ship = fighter();
+        let hit = radar_hit(&ship, vec2(1000.0, 0.0), ship.config.tracker.confirm_snr, 0.0);
+        ship.track(hit);
+        assert!(ship.closest_target.as_ref().is_some_and(|t| t.confirmed));
+    }
+
+    #[test]
+    fn noisy_hit_moves_the_estimate_less() {
+        let _state = fresh_state();
+        let ship = fighter();
+        let config = &ship.config.tracker;
+        let moved = |snr: f64| {
+            let mut t = Target::new(radar_hit(&ship, vec2(1000.0, 0.0), 20.0, 0.0), config);
+            t.add_hit(radar_hit(&ship, vec2(1050.0, 0.0), snr, TICK_LENGTH), config);
+            t.position().distance(vec2(1000.0, 0.0))
+        };
+
+        let noisy = moved(config.min_snr);
+        let clean = moved(40.0);
+        assert!(noisy < clean, "noisy hit moved {}, clean hit moved {}", noisy, clean);
+        assert!(clean > 40.0 && clean <= 50.0);
+        assert!(noisy > 0.0);
+    }
+
+    // Delayed radio reports
+
+    fn report_hit(position: Vec2, velocity: Vec2, time: f64) -> ScanHit {
+        let s = ScanResult { position, velocity, rssi: 0.0, snr: 0.0, class: Class::Fighter };
+        let mut hit = ScanHit::new(s, 5.0, time);
+        hit.shared = true;
+        hit
+    }
+
+    fn fighter() -> Ship {
+        let mut config = ShipConfig::for_class(Class::Fighter);
+        config.telemetry = 0;
+        Ship::with_config(config)
+    }
+
+    #[test]
+    fn late_report_is_extrapolated_by_its_age() {
+        let _state = fresh_state();
+        set_state(SystemState::CurrentTick, 100.0);
+        let velocity = vec2(100.0, -50.0);
+
+        for k in [1, 5, 30] {
+            let age = k as f64 * TICK_LENGTH;
+            let where_it_was = vec2(2000.0, 1000.0);
+            let where_it_is = where_it_was + velocity * age;
+
+            // The same target, reported k ticks late and reported just now
+            let mut late = fighter();
+            late.track(report_hit(where_it_was, velocity, current_time() - age));
+            let mut fresh = fighter();
+            fresh.track(report_hit(where_it_is, velocity, current_time()));
+
+            let late_target = late.closest_target.as_ref().expect("report confirms the target");
+            let fresh_target = fresh.closest_target.as_ref().expect("report confirms the target");
+            assert!((late_target.age() - age).abs() < 1e-9);
+
+            let late_aim = late.calc_future_target(late_target, 100, BULLET_SPEED).position;
+            let fresh_aim = fresh.calc_future_target(fresh_target, 100, BULLET_SPEED).position;
+            assert!(close(late_aim, fresh_aim, 1.0), "k = {}: {} vs {}", k, late_aim, fresh_aim);
+        }
+    }
+
+    #[test]
+    fn out_of_order_report_is_dropped() {
+        let _state = fresh_state();
+        set_state(SystemState::CurrentTick, 100.0);
+        let config = ShipConfig::for_class(Class::Fighter).tracker;
+
+        let mut target = Target::new(report_hit(vec2(1000.0, 0.0), vec2(10.0, 0.0), current_time()), &config);
+        target.add_hit(report_hit(vec2(1010.0, 0.0), vec2(10.0, 0.0), current_time() + 1.0), &config);
+        let position = target.position();
+
+        // Sent before the newest hit, so it arrives with nothing new to say
+        target.add_hit(report_hit(vec2(0.0, 0.0), vec2(0.0, 0.0), current_time() + 0.5), &config);
+        assert_eq!(target.hits.len(), 2);
+        assert!(close(target.position(), position, 1e-9));
+
+        // And a second copy of the newest hit is dropped as well
+        target.add_hit(report_hit(vec2(0.0, 0.0), vec2(0.0, 0.0), current_time() + 1.0), &config);
+        assert_eq!(target.hits.len(), 2);
+    }
+
+    // Radio protocol
+
+    fn round_trip(message: RadioMessage) -> RadioMessage {
+        let packet = RadioMessage::decode(message.encode(42)).expect("decodes");
+        assert_eq!(packet.sender, 42);
+        packet.message
+    }
+
+    #[test]
+    fn target_report_round_trip() {
+        let sent = RadioMessage::TargetReport {
+            class: Class::Frigate,
+            position: vec2(-1234.5, 678.0),
+            velocity: vec2(12.34, -56.78),
+            time: 120.0 * TICK_LENGTH,
+        };
+        match round_trip(sent) {
+            RadioMessage::TargetReport { class, position, velocity, time } => {
+                assert!(class == Class::Frigate);
+                assert!(close(position, vec2(-1234.5, 678.0), POSITION_RESOLUTION));
+                assert!(close(velocity, vec2(12.34, -56.78), VELOCITY_RESOLUTION));
+                assert!((time - 120.0 * TICK_LENGTH).abs() < 1e-9);
+            }
+            _ => panic!("wrong kind"),
+        }
+    }
+
+    #[test]
+    fn position_report_round_trip() {
+        let sent = RadioMessage::PositionReport {
+            position: vec2(500.0, -250.5),
+            velocity: vec2(-3.5, 7.25),
+            heading: 2.0,
+            time: 33.0 * TICK_LENGTH,
+        };
+        match round_trip(sent) {
+            RadioMessage::PositionReport { position, velocity, heading, time } => {
+                assert!(close(position, vec2(500.0, -250.5), POSITION_RESOLUTION));
+                assert!(close(velocity, vec2(-3.5, 7.25), VELOCITY_RESOLUTION));
+                assert!(angle_diff(heading, 2.0).abs() < TAU / 65536.0);
+                assert!((time - 33.0 * TICK_LENGTH).abs() < 1e-9);
+            }
+            _ => panic!("wrong kind"),
+        }
+    }
+
+    #[test]
+    fn attack_order_round_trip() {
+        let sent = RadioMessage::AttackOrder {
+            recipient: 7,
+            position: vec2(10.0, 20.0),
+            velocity: vec2(0.5, -0.5),
+            time: 5.0 * TICK_LENGTH,
+        };
+        match round_trip(sent) {
+            RadioMessage::AttackOrder { recipient, position, velocity, time } => {
+                assert_eq!(recipient, 7);
+                assert!(close(position, vec2(10.0, 20.0), POSITION_RESOLUTION));
+                assert!(close(velocity, vec2(0.5, -0.5), VELOCITY_RESOLUTION));
+                assert!((time - 5.0 * TICK_LENGTH).abs() < 1e-9);
+            }
+            _ => panic!("wrong kind"),
+        }
+    }
+
+    #[test]
+    fn request_for_help_round_trip() {
+        let sent = RadioMessage::RequestForHelp {
+            class: Class::Missile,
+            position: vec2(-100.0, -200.0),
+            threat: vec2(300.5, 400.0),
+            time: 60.0 * TICK_LENGTH,
+        };
+        match round_trip(sent) {
+            RadioMessage::RequestForHelp { class, position, threat, time } => {
+                assert!(class == Class::Missile);
+                assert!(close(position, vec2(-100.0, -200.0), POSITION_RESOLUTION));
+                assert!(close(threat, vec2(300.5, 400.0), POSITION_RESOLUTION));
+                assert!((time - 60.0 * TICK_LENGTH).abs() < 1e-9);
+            }
+            _ => panic!("wrong kind"),
+        }
+    }
+
+    #[test]
+    fn every_class_round_trips() {
+        for class in [
+            Class::Fighter,
+            Class::Frigate,
+            Class::Cruiser,
+            Class::Asteroid,
+            Class::Target,
+            Class::Missile,
+            Class::Torpedo,
+            Class::Unknown,
+        ] {
+            let sent = RadioMessage::TargetReport { class, position: vec2(0.0, 0.0), velocity: vec2(0.0, 0.0), time: 0.0 };
+            match round_trip(sent) {
+                RadioMessage::TargetReport { class: received, .. } => assert!(received == class),
+                _ => panic!("wrong kind"),
+            }
+        }
+    }
+
+    fn report() -> Message {
+        RadioMessage::TargetReport {
+            class: Class::Fighter,
+            position: vec2(100.0, 200.0),
+            velocity: vec2(1.0, 2.0),
+            time: 1.0,
+        }
+        .encode(3)
+    }
+
+    #[test]
+    fn flipped_payload_is_rejected() {
+        for word in 1..4 {
+            let mut msg = report();
+            msg[word] = (msg[word] as u64 ^ 1) as f64;
+            assert!(matches!(RadioMessage::decode(msg), Err(RadioError::Checksum)));
+        }
+    }
+
+    #[test]
+    fn wrong_version_is_rejected() {
+        let mut msg = report();
+        let header = msg[0] as u64;
+        let other_version = (header & !(0xff << 40)) | (PROTOCOL_VERSION + 1) << 40;
+        msg[0] = other_version as f64;
+        assert!(matches!(RadioMessage::decode(msg), Err(RadioError::Version(v)) if v == PROTOCOL_VERSION + 1));
+    }
+
+    #[test]
+    fn unknown_kind_is_rejected() {
+        // A kind from a newer ship, with a checksum that matches
+        let header = PROTOCOL_VERSION << 40 | 9 << 32 | 7 << 16;
+        let payload = [1, 2, 3];
+        let msg = [(header | radio_checksum(header, payload)) as f64, 1.0, 2.0, 3.0];
+        assert!(matches!(RadioMessage::decode(msg), Err(RadioError::UnknownKind(9))));
+    }
+
+    #[test]
+    fn raw_message_is_rejected() {
+        // What the game sends on channel 2: the target's [x, y, vx, vy]
+        let raw = [1234.567, -89.1, 10.25, -3.5];
+        assert!(matches!(RadioMessage::decode(raw), Err(RadioError::NotPacked)));
+
+        // Even whole numbers don't get past the version and checksum
+        let whole = [1000.0, 2000.0, 30.0, 40.0];
+        assert!(RadioMessage::decode(whole).is_err());
+    }
+
+    // Trace
+
+    #[test]
+    fn trace_lists_every_fired_slot_and_damage() {
+        let _state = fresh_state();
+        set_state(SystemState::Health, 100.0);
+        let ship = fighter();
+
+        set_state(SystemState::Health, 80.0);
+        fire(1);
+        fire(3);
+        let trace = ship.trace();
+        assert!(trace.contains("\"fire\":[1, 3]"), "{}", trace);
+        assert!(trace.contains("\"damage\":20"), "{}", trace);
+    }
+
+    // Behavior tree
+
+    #[test]
+    fn track_drops_point_defense_solutions() {
+        let _state = fresh_state();
+        let mut ship = fighter();
+        ship.track(report_hit(vec2(1000.0, 0.0), vec2(0.0, 0.0), current_time()));
+        assert_eq!(ship.act(Action::Track), TreeStatus::Success);
+
+        // Point defense aimed every weapon at a missile behind us, in the same tick
+        let threat = TargetEstimate { estimate_time: 0.0, created_time: 0.0, position: vec2(-500.0, 0.0), angle_error: 0.0 };
+        ship.target_estimate = Some(threat);
+        for w in ship.weapons.iter_mut() {
+            w.estimate = Some(threat);
+        }
+        ship.threat_solutions = true;
+
+        assert_eq!(ship.act(Action::Track), TreeStatus::Success);
+        assert!(ship.target_estimate.unwrap().position.x > 0.0);
+        for w in &ship.weapons {
+            assert!(w.estimate.unwrap().position.x > 0.0);
+        }
+    }
+
+    // Missile guidance
+
+    // Flies a missile at a target moving in a straight line, integrating the acceleration and
+    // torque it commands each tick like the game would.
+    // Returns the closest it got, and if it exploded.
+    fn fly_missile(augmented_nav: bool, target_start: Vec2, target_velocity: Vec2) -> (f64, bool) {
+        set_state(SystemState::Class, 5.0); // Class::Missile
+        set_state(SystemState::MaxForwardAcceleration, 300.0);
+        set_state(SystemState::MaxLateralAcceleration, 100.0);
+        set_state(SystemState::MaxAngularAcceleration, 4.0 * TAU);
+        let mut config = ShipConfig::for_class(Class::Missile);
+        config.telemetry = 0;
+        config.augmented_nav = augmented_nav;
+        let mut ship = Ship::with_config(config);
+
+        let mut p = vec2(0.0, 0.0);
+        let mut v = vec2(100.0, 0.0);
+        let mut h = 0.0;
+        let mut w = 0.0;
+        let mut closest = f64::MAX;
+        let radar = radar_internal::radar_contact_indices(0);
+
+        for tick in 0..(20.0 / TICK_LENGTH) as u32 {
+            let target = target_start + target_velocity * (tick as f64 * TICK_LENGTH);
+            closest = closest.min(p.distance(target));
+
+            set_state(SystemState::CurrentTick, tick as f64);
+            set_state(SystemState::PositionX, p.x);
+            set_state(SystemState::PositionY, p.y);
+            set_state(SystemState::VelocityX, v.x);
+            set_state(SystemState::VelocityY, v.y);
+            set_state(SystemState::Heading, h);
+            set_state(SystemState::AngularVelocity, w);
+            radar_contact(target, 30.0);
+            set_state(radar.velocity[0], target_velocity.x);
+            set_state(radar.velocity[1], target_velocity.y);
+
+            ship.tick();
+            if read_system_state(SystemState::Explode) != 0.0 {
+                return (closest, true);
+            }
+
+            let a = vec2(read_system_state(SystemState::AccelerateX), read_system_state(SystemState::AccelerateY)).rotate(h);
+            v += a * TICK_LENGTH;
+            p += v * TICK_LENGTH;
+            w += read_system_state(SystemState::Torque) * TICK_LENGTH;
+            h += w * TICK_LENGTH;
+            for command in [SystemState::AccelerateX, SystemState::AccelerateY, SystemState::Torque] {
+                set_state(command, 0.0);
+            }
+        }
+        (closest, false)
+    }
+
+    #[test]
+    fn missile_hits_a_crossing_target() {
+        let blast_radius = ShipConfig::for_class(Class::Missile).blast_radius;
+        for augmented_nav in [false, true] {
+            for (start, velocity) in [
+                (vec2(4000.0, 1500.0), vec2(-100.0, -150.0)),
+                (vec2(3000.0, -2000.0), vec2(0.0, 250.0)),
+                (vec2(-2000.0, 2000.0), vec2(200.0, 0.0)),
+            ] {
+                let _state = fresh_state();
+                let (closest, exploded) = fly_missile(augmented_nav, start, velocity);
+                assert!(closest < blast_radius, "augmented_nav {}: closest approach {}", augmented_nav, closest);
+                assert!(exploded, "augmented_nav {}: never exploded", augmented_nav);
+            }
+        }
+    }
+
+    // Launchers
+
+    // Launches at a target of the given class straight ahead, and returns the slots fired
+    fn cruiser_launches_at(class: Class) -> Vec<usize> {
+        let _state = fresh_state();
+        set_state(SystemState::Class, 2.0); // Class::Cruiser
+        let mut config = ShipConfig::for_class(Class::Cruiser);
+        config.telemetry = 0;
+        config.max_missiles_per_target = 3;
+        let mut ship = Ship::with_config(config);
+
+        let mut hit = report_hit(vec2(1500.0, 0.0), vec2(0.0, 0.0), current_time());
+        hit.result.class = class;
+        ship.track(hit);
+        ship.launch_missiles();
+        fired_slots()
+    }
+
+    #[test]
+    fn torpedoes_are_saved_for_bigger_ships() {
+        assert_eq!(cruiser_launches_at(Class::Fighter), vec![1, 2]);
+        assert_eq!(cruiser_launches_at(Class::Cruiser), vec![1, 2, 3]);
+    }
+
+    // Sharing targets
+
+    #[test]
+    fn every_enemy_is_shared_in_turn() {
+        let _state = fresh_state();
+        let mut config = ShipConfig::for_class(Class::Fighter);
+        config.telemetry = 0;
+        config.raw_radio_channel = Some(2);
+        let mut ship = Ship::with_config(config);
+        let enemies = [vec2(1000.0, 0.0), vec2(-3000.0, 2000.0), vec2(0.0, 5000.0)];
+        for p in enemies {
+            let mut hit = report_hit(p, vec2(0.0, 0.0), current_time());
+            hit.shared = false;
+            ship.track(hit);
+        }
+        assert_eq!(ship.enemies.len(), 3);
+        for t in ship.enemies.iter_mut() {
+            t.confirmed = true;
+        }
+
+        // Radio 0 has the raw channel to itself, so the team talks on radio 1
+        let send = radio_internal::radio_indices(1).send;
+        let mut shared = Vec::new();
+        for tick in 0..12 * ship.config.target_report_period {
+            set_state(SystemState::CurrentTick, tick as f64);
+            ship.radio();
+            ship.radio.transmit();
+            if read_system_state(send) != 0.0 {
+                let msg = radio_internal::radio_indices(1).data.map(read_system_state);
+                if let RadioMessage::TargetReport { position, .. } = RadioMessage::decode(msg).expect("decodes").message {
+                    shared.push(position);
+                }
+                set_state(send, 0.0);
+            }
+        }
+
+        for p in enemies {
+            let times = shared.iter().filter(|s| close(**s, p, 1.0)).count();
+            assert!(times >= 2, "{:?} shared {} times", p, times);
+        }
+    }
+
+    #[test]
+    fn missiles_stay_out_of_the_fleet() {
+        let _state = fresh_state();
+        set_state(SystemState::Class, 5.0); // Class::Missile
+        let mut config = ShipConfig::for_class(Class::Missile);
+        config.telemetry = 0;
+        let mut ship = Ship::with_config(config);
+        ship.wingmen.push(Wingman { id: 0, position: vec2(0.0, 0.0), velocity: vec2(0.0, 0.0), heading: 0.0, time: 0.0 });
+        set_state(SystemState::Id, 7.0);
+
+        for tick in 0..20 {
+            set_state(SystemState::CurrentTick, tick as f64);
+            ship.radio();
+            ship.radio.transmit();
+        }
+        assert!(ship.leader.is_none());
+        assert!(ship.formation.is_none());
+        assert!(!ship.radio.is_queued(ship.config.team_channel, RadioMessage::TARGET_REPORT));
+        for r in 0..ship.radio.radios {
+            assert_eq!(read_system_state(radio_internal::radio_indices(r).send), 0.0);
+        }
+    }
+
+    // Behaviors
+
+    // A fighter at full health with a confirmed target `distance` ahead of it
+    fn fighter_with_target(distance: f64) -> Ship {
+        set_state(SystemState::Health, 100.0);
+        let mut ship = fighter();
+        let hit = radar_hit(&ship, vec2(distance, 0.0), ship.config.tracker.confirm_snr, 0.0);
+        ship.track(hit);
+        ship
+    }
+
+    #[test]
+    fn approach_becomes_engage_at_max_range() {
+        let _state = fresh_state();
+        let max_range = ShipConfig::for_class(Class::Fighter).max_range;
+        let mut ship = fighter_with_target(max_range + 10.0);
+        ship.behavior = Behavior::Approach;
+        assert_eq!(ship.next_behavior(), Behavior::Approach);
+
+        let mut ship = fighter_with_target(max_range - 10.0);
+        ship.behavior = Behavior::Approach;
+        assert_eq!(ship.next_behavior(), Behavior::Engage);
+    }
+
+    #[test]
+    fn engage_holds_until_well_out_of_range() {
+        let _state = fresh_state();
+        let max_range = ShipConfig::for_class(Class::Fighter).max_range;
+        let mut ship = fighter_with_target(max_range * 1.2);
+        ship.behavior = Behavior::Engage;
+        assert_eq!(ship.next_behavior(), Behavior::Engage);
+
+        let mut ship = fighter_with_target(max_range * 1.4);
+        ship.behavior = Behavior::Engage;
+        assert_eq!(ship.next_behavior(), Behavior::Approach);
+    }
+
+    #[test]
+    fn low_health_evades() {
+        let _state = fresh_state();
+        let mut ship = fighter_with_target(1000.0);
+        ship.behavior = Behavior::Engage;
+        set_state(SystemState::Health, 100.0 * ship.config.evade_health + 1.0);
+        assert_eq!(ship.next_behavior(), Behavior::Engage);
+        set_state(SystemState::Health, 100.0 * ship.config.evade_health - 1.0);
+        assert_eq!(ship.next_behavior(), Behavior::Evade);
+    }
+
+    #[test]
+    fn no_target_returns_to_formation_if_we_have_one() {
+        let _state = fresh_state();
+        let mut ship = fighter();
+        assert_eq!(ship.next_behavior(), Behavior::Search);
+        ship.formation = Some(vec2(-200.0, 200.0));
+        assert_eq!(ship.next_behavior(), Behavior::ReturnToFormation);
+    }
+
+    // Turrets
+
+    // A cruiser pointing along +x, its turret told to aim at `target`
+    fn cruiser_aiming_at(target: Vec2) -> Ship {
+        set_state(SystemState::Class, 2.0); // Class::Cruiser
+        let mut config = ShipConfig::for_class(Class::Cruiser);
+        config.telemetry = 0;
+        let mut ship = Ship::with_config(config);
+        let estimate = TargetEstimate { estimate_time: 0.0, created_time: 0.0, position: target, angle_error: 0.01 };
+        ship.weapons[0].estimate = Some(estimate);
+        ship
+    }
+
+    #[test]
+    fn turret_turns_at_most_its_turn_rate() {
+        let _state = fresh_state();
+        let mut ship = cruiser_aiming_at(vec2(-2000.0, 10.0));
+        let step = ship.weapons[0].weapon.turn_rate * TICK_LENGTH;
+        let ticks_to_turn = (PI / step).ceil() as usize;
+
+        let mut angle = ship.weapons[0].turret_angle;
+        for tick in 0..ticks_to_turn + 5 {
+            ship.aim_turrets();
+            let w = &ship.weapons[0];
+            assert!(angle_diff(angle, w.turret_angle).abs() <= step + 1e-9, "turned too far on tick {}", tick);
+            if tick + 1 < ticks_to_turn {
+                assert!(!w.on_solution, "on solution before catching up, tick {}", tick);
+            }
+            angle = w.turret_angle;
+        }
+        assert!(ship.weapons[0].on_solution);
+    }
+
+    #[test]
+    fn turret_fires_with_the_hull_pointed_away() {
+        let _state = fresh_state();
+        let target = vec2(-2000.0, 0.0);
+        let mut ship = cruiser_aiming_at(target);
+        for _ in 0..200 {
+            ship.aim_turrets();
+        }
+        assert!(angle_diff(heading(), target.angle()).abs() > 3.0, "the hull points away");
+        assert!(ship.weapons[0].on_solution);
+
+        ship.update_weapon_readiness();
+        ship.fire(&ship.weapons[0].estimate.unwrap());
+        assert_eq!(read_system_state(SystemState::Fire0), 1.0);
+        assert_eq!(read_system_state(SystemState::Aim0), target.angle());
+    }
+
+    // Threats
+
+    fn missile_hit(position: Vec2, velocity: Vec2, time: f64) -> ScanHit {
+        let s = ScanResult { position, velocity, rssi: 0.0, snr: 30.0, class: Class::Missile };
+        ScanHit::new(s, 1.0, time)
+    }
+
+    #[test]
+    fn accelerating_missile_is_one_threat_even_for_a_cruiser() {
+        let _state = fresh_state();
+        set_state(SystemState::Class, 2.0); // Class::Cruiser
+        let mut config = ShipConfig::for_class(Class::Cruiser);
+        config.telemetry = 0;
+        let mut ship = Ship::with_config(config);
+
+        let a = vec2(-Class::Missile.default_stats().max_forward_acceleration, 0.0);
+        for tick in 0..30 {
+            let t = tick as f64 * TICK_LENGTH;
+            let hit = missile_hit(vec2(5000.0, 0.0) + 0.5 * a * t * t, a * t, t);
+            ship.track(hit);
+        }
+        assert_eq!(ship.threats.len(), 1);
+        assert_eq!(ship.threats[0].hits.len(), 30);
+    }
+
+    #[test]
+    fn incoming_threats_are_soonest_first_and_skip_ones_moving_away() {
+        let _state = fresh_state();
+        let mut ship = fighter();
+        ship.track(missile_hit(vec2(3000.0, 0.0), vec2(-500.0, 0.0), 0.0));
+        ship.track(missile_hit(vec2(0.0, 1000.0), vec2(0.0, -500.0), 0.0));
+        ship.track(missile_hit(vec2(-1000.0, 0.0), vec2(-500.0, 0.0), 0.0)); // moving away
+        assert_eq!(ship.threats.len(), 3);
+
+        let incoming = ship.incoming_threats();
+        assert_eq!(incoming.len(), 2);
+        assert!(close(vec2(incoming[0].0, 0.0), vec2(2.0, 0.0), 1e-6));
+        assert!(close(vec2(incoming[1].0, 0.0), vec2(6.0, 0.0), 1e-6));
+        assert!(close(incoming[0].1.position(), vec2(0.0, 1000.0), 1e-6));
+    }
+
+    #[test]
+    fn close_threat_switches_to_point_defense() {
+        let _state = fresh_state();
+        let mut ship = fighter_with_target(1000.0);
+        ship.behavior = Behavior::Engage;
+        let speed = 500.0;
+
+        // Further out than `point_defense_time` we keep fighting
+        let far = speed * (ship.config.point_defense_time + 1.0);
+        ship.track(missile_hit(vec2(0.0, far), vec2(0.0, -speed), 0.0));
+        assert_eq!(ship.next_behavior(), Behavior::Engage);
+
+        ship.threats.clear();
+        let near = speed * (ship.config.point_defense_time - 1.0);
+        ship.track(missile_hit(vec2(0.0, near), vec2(0.0, -speed), 0.0));
+        assert_eq!(ship.next_behavior(), Behavior::PointDefense);
+    }
+
+    // Fleet
+
+    // A position report from `sender`, sent at the current tick
+    fn hear_position(ship: &mut Ship, sender: u32, position: Vec2) {
+        let message = RadioMessage::PositionReport { position, velocity: vec2(0.0, 0.0), heading: 0.0, time: current_time() };
+        ship.handle_radio(RadioPacket { sender, message });
+    }
+
+    #[test]
+    fn lowest_id_leads_until_it_goes_quiet() {
+        let _state = fresh_state();
+        set_state(SystemState::Id, 5.0);
+        let mut ship = fighter();
+        let timeout = ship.config.leader_timeout;
+
+        hear_position(&mut ship, 3, vec2(0.0, 500.0));
+        hear_position(&mut ship, 2, vec2(0.0, 0.0));
+        ship.update_fleet();
+        assert_eq!(ship.leader, Some(2));
+
+        // Only 3 keeps reporting; 2 still leads until it has been quiet for `leader_timeout`
+        let mut tick = 0;
+        while (tick as f64) * TICK_LENGTH < timeout + TICK_LENGTH {
+            tick += 10;
+            set_state(SystemState::CurrentTick, tick as f64);
+            hear_position(&mut ship, 3, vec2(0.0, 500.0));
+            ship.update_fleet();
+            let expected = if current_time() < timeout { 2 } else { 3 };
+            assert_eq!(ship.leader, Some(expected), "at {}s", current_time());
+        }
+        assert_eq!(ship.leader, Some(3));
+        assert!(ship.formation.is_some_and(|f| close(f, vec2(0.0, 500.0), ship.config.formation_spacing * 1.5)));
+    }
+
+    #[test]
+    fn ranks_fill_a_v_behind_the_leader() {
+        let _state = fresh_state();
+        let spacing = ShipConfig::for_class(Class::Fighter).formation_spacing;
+        let leader = vec2(1000.0, 1000.0);
+        let expected = [vec2(-1.0, 1.0), vec2(-1.0, -1.0), vec2(-2.0, 2.0)];
+
+        for (rank, offset) in expected.into_iter().enumerate() {
+            set_state(SystemState::Id, (rank + 2) as f64);
+            let mut ship = fighter();
+            hear_position(&mut ship, 1, leader);
+            hear_position(&mut ship, 2, vec2(0.0, 0.0));
+            hear_position(&mut ship, 3, vec2(0.0, 0.0));
+            ship.update_fleet();
+            assert_eq!(ship.leader, Some(1));
+            let formation = ship.formation.expect("has a place in the formation");
+            assert!(close(formation, leader + offset * spacing, 1e-6), "rank {}: {}", rank + 1, formation);
+        }
+    }
+
+    #[test]
+    fn target_reports_are_spread_out_by_id() {
+        let _state = fresh_state();
+        let sent_on = |id: u32| {
+            clear_state();
+            set_state(SystemState::Id, id as f64);
+            let mut ship = fighter();
+            let mut hit = report_hit(vec2(1000.0, 0.0), vec2(0.0, 0.0), 0.0);
+            hit.shared = false;
+            ship.track(hit);
+            ship.enemies[0].confirmed = true;
+
+            let mut ticks = Vec::new();
+            for tick in 1..=2 * ship.config.target_report_period {
+                set_state(SystemState::CurrentTick, tick as f64);
+                ship.share_targets();
+                if ship.radio.is_queued(ship.config.team_channel, RadioMessage::TARGET_REPORT) {
+                    ticks.push(tick);
+                    ship.radio = RadioManager::new(ship.config.radios);
+                }
+            }
+            ticks
+        };
+
+        let period = ShipConfig::for_class(Class::Fighter).target_report_period;
+        let first = sent_on(1);
+        let second = sent_on(2);
+        assert_eq!(first.len(), 2);
+        assert_eq!(first[1] - first[0], period);
+        assert_eq!(second.len(), 2);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn ships_agree_on_targets_seen_at_different_times() {
+        let _state = fresh_state();
+        let now_tick = 60;
+        let now = now_tick as f64 * TICK_LENGTH;
+        let still = vec2(1000.0, 0.0);
+        let moving_now = vec2(500.0, 0.0);
+        let velocity = vec2(-1500.0, 0.0);
+
+        // Both ships sit on the same spot, so only their ids break the tie
+        let assigned = |me: u32, other: u32, seen_at: f64| {
+            clear_state();
+            set_state(SystemState::Id, me as f64);
+            let mut config = ShipConfig::for_class(Class::Fighter);
+            config.telemetry = 0;
+            config.max_ships_per_target = 1;
+            let mut ship = Ship::with_config(config);
+            set_state(SystemState::CurrentTick, now_tick as f64);
+            ship.wingmen.push(Wingman { id: other, position: vec2(0.0, 0.0), velocity: vec2(0.0, 0.0), heading: 0.0, time: now });
+
+            let moving_then = moving_now - velocity * (now - seen_at);
+            ship.track(report_hit(still, vec2(0.0, 0.0), seen_at));
+            ship.track(report_hit(moving_then, velocity, seen_at));
+            for t in ship.enemies.iter_mut() {
+                t.confirmed = true;
+            }
+            ship.assign_targets();
+            assert!(ship.assigned);
+            ship.closest_target.as_ref().unwrap().future_position(now, true)
+        };
+
+        // Ship 1 last saw the enemies a second ago, ship 2 sees them now
+        let first = assigned(1, 2, 0.0);
+        let second = assigned(2, 1, now);
+        assert!(close(first, moving_now, 1.0), "ship 1 has the closest enemy, got {}", first);
+        assert!(close(second, still, 1.0), "ship 2 has the other one, got {}", second);
     }
-    
-    (
-        calc_first_angle(a, b, c),
-        calc_first_angle(b, a, c),
-        calc_first_angle(c, a, b),
-    )
 }
@@ -0,0 +1,594 @@
+// Offline tools for 9_radio.rs
+// The game doesn't run any of this. It's for looking at and tuning the ship from a test
+// or a runner that can play battles, e.g. oort's simulator.
+
+use super::*;
+
+// What the ship did in a replayed tick
+#[derive(Debug)]
+pub struct TickOutputs {
+    pub position: Vec2,
+    pub heading: f64,
+    pub lines: Vec<(Vec2, Vec2, u32)>, // everything drawn this tick
+    pub acceleration: Vec2, // relative to the ship's heading
+    pub torque: f64,
+    pub fire: Vec<usize>, // weapon and launcher slots fired
+    pub radar_heading: f64,
+    pub radar_width: f64,
+    pub radio_channel: usize,
+    pub radio_send: Option<Message>,
+}
+
+// Plays back the lines written with the Record category on, one tick per line
+pub fn replay(recording: &str) -> Vec<TickOutputs> {
+    let mut ship: Option<Ship> = None;
+    let mut config: Option<Vec<(String, f64)>> = None;
+    let mut outputs = Vec::new();
+
+    for line in recording.lines() {
+        if let Some(line) = line.trim().strip_prefix("cfg ") {
+            let settings = line
+                .split(' ')
+                .filter_map(|setting| setting.split_once('='))
+                .filter_map(|(name, value)| Some((name.to_string(), parse_config_value(value)?)))
+                .collect();
+            config = Some(settings);
+            continue;
+        }
+        let Some(line) = line.trim().strip_prefix("rec ") else {
+            continue;
+        };
+        let mut values = line.split(' ');
+        let radios = values.next().and_then(|v| v.parse().ok()).unwrap_or(1);
+
+        for (input, value) in recorded_inputs(radios).into_iter().zip(values) {
+            oort_api::sys::write_system_state(input, value.parse().unwrap_or(0.0));
+        }
+
+        // The ship is created on the first tick, just like in the game,
+        // with the recorded config. Older recordings without one get our own.
+        DRAWN_LINES.with(|lines| *lines.borrow_mut() = Some(Vec::new()));
+        let ship = ship.get_or_insert_with(|| match &config {
+            Some(settings) => {
+                let mut config = ShipConfig::for_class(class());
+                for (name, value) in settings {
+                    config.set(name, *value);
+                }
+                Ship::with_config(config)
+            }
+            None => Ship::new(),
+        });
+        ship.tick();
+
+        let radar = radar_internal::radar_control_indices(0);
+        let radio = radio_internal::radio_indices(0);
+        outputs.push(TickOutputs {
+            position: position(),
+            heading: heading(),
+            lines: DRAWN_LINES.with(|lines| lines.take()).unwrap_or_default(),
+            acceleration: vec2(
+                read_system_state(SystemState::AccelerateX),
+                read_system_state(SystemState::AccelerateY),
+            ),
+            torque: read_system_state(SystemState::Torque),
+            fire: fired_slots(),
+            radar_heading: read_system_state(radar.heading),
+            radar_width: read_system_state(radar.width),
+            radio_channel: read_system_state(radio.channel) as usize,
+            radio_send: if read_system_state(radio.send) != 0.0 {
+                Some(radio.data.map(read_system_state))
+            } else {
+                None
+            },
+        });
+
+        // The game clears commands between ticks
+        for command in [SystemState::AccelerateX, SystemState::AccelerateY, SystemState::Torque, SystemState::Fire0, SystemState::Fire1, SystemState::Fire2, SystemState::Fire3, radio.send] {
+            oort_api::sys::write_system_state(command, 0.0);
+        }
+        oort_api::dbg::reset();
+    }
+
+    outputs
+}
+
+// Draws a replayed battle as an SVG: our path, our heading at the end,
+// and the debug lines from every `every` ticks (the last tick is drawn brightest)
+pub fn render_svg(ticks: &[TickOutputs], every: usize) -> String {
+    let Some(last) = ticks.last() else {
+        return String::new();
+    };
+    let every = every.max(1);
+    let is_drawn = |i: usize| i.is_multiple_of(every) || i == ticks.len() - 1;
+
+    // Fit everything in view
+    let mut min = last.position;
+    let mut max = last.position;
+    let mut fit = |p: Vec2| {
+        if p.x.is_finite() && p.y.is_finite() {
+            min = vec2(min.x.min(p.x), min.y.min(p.y));
+            max = vec2(max.x.max(p.x), max.y.max(p.y));
+        }
+    };
+    for (i, tick) in ticks.iter().enumerate() {
+        fit(tick.position);
+        if is_drawn(i) {
+            for (a, b, _) in &tick.lines {
+                fit(*a);
+                fit(*b);
+            }
+        }
+    }
+    let margin = 100.0;
+    let size = max - min + vec2(2.0 * margin, 2.0 * margin);
+    let stroke = size.x.max(size.y) / 500.0;
+
+    // oort's y axis points up, SVG's points down
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n<g transform=\"scale(1,-1)\">\n",
+        min.x - margin, -(max.y + margin), size.x, size.y,
+    );
+    svg.push_str(&format!(
+        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"black\"/>\n",
+        min.x - margin, min.y - margin, size.x, size.y,
+    ));
+
+    for (i, tick) in ticks.iter().enumerate() {
+        if !is_drawn(i) {
+            continue;
+        }
+        let opacity = if i == ticks.len() - 1 { 1.0 } else { 0.3 };
+        for (a, b, color) in &tick.lines {
+            svg.push_str(&format!(
+                "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"#{:06x}\" stroke-width=\"{}\" opacity=\"{}\"/>\n",
+                a.x, a.y, b.x, b.y, color, stroke, opacity,
+            ));
+        }
+    }
+
+    let path: Vec<String> = ticks.iter().map(|t| format!("{},{}", t.position.x, t.position.y)).collect();
+    svg.push_str(&format!(
+        "<polyline points=\"{}\" fill=\"none\" stroke=\"white\" stroke-width=\"{}\"/>\n",
+        path.join(" "), stroke,
+    ));
+
+    let nose = last.position + vec2(10.0 * stroke, 0.0).rotate(last.heading);
+    svg.push_str(&format!(
+        "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"white\" stroke-width=\"{}\"/>\n",
+        last.position.x, last.position.y, nose.x, nose.y, 2.0 * stroke,
+    ));
+
+    svg.push_str("</g>\n</svg>\n");
+    svg
+}
+
+// Parameter sweeps
+// These only pick configs and add up the results. Nothing in this repo can play a battle,
+// so a runner that can (e.g. oort's simulator) passes `battle`, which plays one seeded battle
+// with the given environment and reports how it went.
+pub struct BattleResult {
+    pub won: bool,
+    pub time_to_kill: Option<f64>, // seconds, if we destroyed the enemy
+}
+
+#[derive(Debug)]
+pub struct SweepReport {
+    pub config: Vec<(&'static str, f64)>,
+    pub battles: usize,
+    pub win_rate: f64,
+    pub average_time_to_kill: Option<f64>,
+}
+
+// Every combination of the given values
+pub fn sweep_grid(values: &[(&'static str, Vec<f64>)]) -> Vec<Vec<(&'static str, f64)>> {
+    let mut configs = vec![Vec::new()];
+    for (name, options) in values {
+        configs = configs
+            .iter()
+            .flat_map(|config| options.iter().map(move |v| {
+                let mut config: Vec<(&'static str, f64)> = config.clone();
+                config.push((*name, *v));
+                config
+            }))
+            .collect();
+    }
+    configs
+}
+
+// xorshift, so the same seed always gives the same numbers
+struct SeededRng(u64);
+
+impl SeededRng {
+    fn new(seed: u64) -> SeededRng {
+        SeededRng(seed.max(1))
+    }
+
+    // Between min and max
+    fn next(&mut self, min: f64, max: f64) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        min + (max - min) * ((self.0 >> 11) as f64 / (1u64 << 53) as f64)
+    }
+}
+
+// `samples` random picks between each (name, min, max)
+pub fn sweep_random(ranges: &[(&'static str, f64, f64)], samples: usize, seed: u64) -> Vec<Vec<(&'static str, f64)>> {
+    let mut rng = SeededRng::new(seed);
+    (0..samples)
+        .map(|_| ranges.iter().map(|(name, min, max)| (*name, rng.next(*min, *max))).collect())
+        .collect()
+}
+
+// The environment to run a battle with, e.g. "max_range=2500\npassing_speed=150\n"
+pub fn sweep_environment(config: &[(&'static str, f64)]) -> String {
+    config.iter().map(|(name, v)| format!("{}={}\n", name, v)).collect()
+}
+
+pub fn run_sweep(
+    configs: &[Vec<(&'static str, f64)>],
+    seeds: &[u64],
+    mut battle: impl FnMut(&str, u64) -> BattleResult,
+) -> Vec<SweepReport> {
+    configs
+        .iter()
+        .map(|config| {
+            let environment = sweep_environment(config);
+            let results: Vec<BattleResult> = seeds.iter().map(|seed| battle(&environment, *seed)).collect();
+
+            let wins = results.iter().filter(|r| r.won).count();
+            let kills: Vec<f64> = results.iter().filter_map(|r| r.time_to_kill).collect();
+
+            SweepReport {
+                config: config.clone(),
+                battles: results.len(),
+                win_rate: if results.is_empty() { 0.0 } else { wins as f64 / results.len() as f64 },
+                average_time_to_kill: if kills.is_empty() { None } else { Some(kills.iter().sum::<f64>() / kills.len() as f64) },
+            }
+        })
+        .collect()
+}
+
+// League
+// Rates bot versions against each other with Elo. Like sweeps, the runner passes `battle`
+// to play a single match, and this only picks the pairings and keeps score.
+// Each tutorial defines its own `Ship`, so e.g. 8_search.rs and 9_radio.rs have to be
+// built as separate bots by the runner, not from one crate.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MatchResult {
+    Win,  // the first bot won
+    Loss, // the second bot won
+    Draw,
+}
+
+impl MatchResult {
+    fn score(&self) -> f64 {
+        match self {
+            MatchResult::Win => 1.0,
+            MatchResult::Loss => 0.0,
+            MatchResult::Draw => 0.5,
+        }
+    }
+}
+
+// Where a bot starts a match: (position, heading)
+pub type StartPosition = (Vec2, f64);
+
+pub struct League {
+    pub ratings: Vec<(String, f64)>,
+    pub results: Vec<(String, String, MatchResult)>,
+    k: f64, // how far one match moves a rating
+}
+
+impl League {
+    pub fn new(bots: &[&str]) -> League {
+        League {
+            ratings: bots.iter().map(|b| (b.to_string(), 1500.0)).collect(),
+            results: Vec::new(),
+            k: 32.0,
+        }
+    }
+
+    pub fn rating(&self, bot: &str) -> f64 {
+        self.ratings.iter().find(|(b, _)| b == bot).map_or(1500.0, |(_, r)| *r)
+    }
+
+    fn set_rating(&mut self, bot: &str, rating: f64) {
+        match self.ratings.iter_mut().find(|(b, _)| b == bot) {
+            Some((_, r)) => *r = rating,
+            None => self.ratings.push((bot.to_string(), rating)),
+        }
+    }
+
+    pub fn record(&mut self, a: &str, b: &str, result: MatchResult) {
+        let ra = self.rating(a);
+        let rb = self.rating(b);
+        let expected = 1.0 / (1.0 + 10f64.powf((rb - ra) / 400.0));
+        let change = self.k * (result.score() - expected);
+
+        self.set_rating(a, ra + change);
+        self.set_rating(b, rb - change);
+        self.results.push((a.to_string(), b.to_string(), result));
+    }
+
+    // Every pair of bots plays twice from mirrored starting positions, swapping sides,
+    // so neither gets a better start
+    pub fn play_round(
+        &mut self,
+        seed: u64,
+        mut battle: impl FnMut(&str, &str, StartPosition, StartPosition, u64) -> MatchResult,
+    ) {
+        let mut rng = SeededRng::new(seed);
+        let bots: Vec<String> = self.ratings.iter().map(|(b, _)| b.clone()).collect();
+
+        for i in 0..bots.len() {
+            for j in (i + 1)..bots.len() {
+                let p = vec2(rng.next(2_000.0, 10_000.0), 0.0).rotate(rng.next(0.0, TAU));
+                let start = (p, (-p).angle());
+                let mirrored = (-p, p.angle());
+                let match_seed = rng.next(0.0, u32::MAX as f64) as u64;
+
+                let result = battle(&bots[i], &bots[j], start, mirrored, match_seed);
+                self.record(&bots[i], &bots[j], result);
+                let result = battle(&bots[j], &bots[i], start, mirrored, match_seed);
+                self.record(&bots[j], &bots[i], result);
+            }
+        }
+    }
+
+    // One "a b result" line per match; ratings are rebuilt from these by `load`
+    pub fn save(&self) -> String {
+        self.results.iter().map(|(a, b, r)| format!("{} {} {:?}\n", a, b, r)).collect()
+    }
+
+    pub fn load(bots: &[&str], saved: &str) -> League {
+        let mut league = League::new(bots);
+        for line in saved.lines() {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let result = match parts.get(2) {
+                Some(&"Win") => MatchResult::Win,
+                Some(&"Loss") => MatchResult::Loss,
+                Some(&"Draw") => MatchResult::Draw,
+                _ => continue,
+            };
+            league.record(parts[0], parts[1], result);
+        }
+        league
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::tests::{clear_state, fresh_state};
+
+    // Replay
+
+    fn set_state(state: SystemState, value: f64) {
+        oort_api::sys::write_system_state(state, value);
+    }
+
+    // Plays a fighter against a crossing target, recording it as the game would,
+    // and returns the recording with what the ship did
+    fn record_battle(config: ShipConfig) -> (String, Vec<(Vec2, f64, Vec<usize>)>) {
+        set_state(SystemState::Class, 0.0); // Class::Fighter
+        set_state(SystemState::Health, 100.0);
+        set_state(SystemState::MaxForwardAcceleration, 60.0);
+        set_state(SystemState::MaxBackwardAcceleration, 30.0);
+        set_state(SystemState::MaxLateralAcceleration, 30.0);
+        set_state(SystemState::MaxAngularAcceleration, TAU);
+
+        let mut recording = record_config(&config);
+        let mut ship = Ship::with_config(config);
+        let mut did = Vec::new();
+        let radar = radar_internal::radar_contact_indices(0);
+
+        for tick in 0..60 {
+            let target = vec2(2000.0, 500.0) + vec2(-30.0, 20.0) * (tick as f64 * TICK_LENGTH);
+            set_state(SystemState::CurrentTick, tick as f64);
+            set_state(radar.found, 1.0);
+            set_state(radar.position[0], target.x);
+            set_state(radar.position[1], target.y);
+            set_state(radar.velocity[0], -30.0);
+            set_state(radar.velocity[1], 20.0);
+            set_state(radar.snr, 30.0);
+
+            recording.push('\n');
+            recording.push_str(&record_inputs(ship.config.radios));
+            ship.tick();
+
+            did.push((
+                vec2(read_system_state(SystemState::AccelerateX), read_system_state(SystemState::AccelerateY)),
+                read_system_state(SystemState::Torque),
+                fired_slots(),
+            ));
+            for command in [SystemState::AccelerateX, SystemState::AccelerateY, SystemState::Torque, SystemState::Fire0, SystemState::Fire1, SystemState::Fire2, SystemState::Fire3] {
+                set_state(command, 0.0);
+            }
+        }
+        (recording, did)
+    }
+
+    fn replayed(recording: &str) -> Vec<(Vec2, f64, Vec<usize>)> {
+        replay(recording).into_iter().map(|t| (t.acceleration, t.torque, t.fire)).collect()
+    }
+
+    #[test]
+    fn replay_does_what_the_recorded_ship_did() {
+        let _state = fresh_state();
+        let mut config = ShipConfig::for_class(Class::Fighter);
+        config.telemetry = 0;
+        let (recording, did) = record_battle(config);
+        assert!(did.iter().any(|(a, torque, _)| a.length() > 0.0 && *torque != 0.0));
+
+        clear_state();
+        assert_eq!(replayed(&recording), did);
+    }
+
+    #[test]
+    fn replay_uses_the_recorded_config() {
+        let _state = fresh_state();
+        let mut config = ShipConfig::for_class(Class::Fighter);
+        config.telemetry = 0;
+        config.use_radar = false; // e.g. a sweep trying without the radar
+        let (recording, did) = record_battle(config);
+
+        clear_state();
+        assert_eq!(replayed(&recording), did);
+
+        // Without the config line, the defaults don't do the same
+        clear_state();
+        let without_config: String = recording.lines().skip(1).map(|l| format!("{}\n", l)).collect();
+        assert_ne!(replayed(&without_config), did);
+    }
+
+    #[test]
+    fn lines_are_only_kept_while_replaying() {
+        draw_line(vec2(0.0, 0.0), vec2(1.0, 1.0), GREEN);
+        assert!(DRAWN_LINES.with(|lines| lines.borrow().is_none()));
+    }
+
+    // SVG
+
+    fn tick_at(position: Vec2, lines: Vec<(Vec2, Vec2, u32)>) -> TickOutputs {
+        TickOutputs {
+            position,
+            heading: 0.0,
+            lines,
+            acceleration: vec2(0.0, 0.0),
+            torque: 0.0,
+            fire: Vec::new(),
+            radar_heading: 0.0,
+            radar_width: 0.0,
+            radio_channel: 0,
+            radio_send: None,
+        }
+    }
+
+    #[test]
+    fn svg_fits_the_battle_and_flips_y() {
+        let ticks = vec![
+            tick_at(vec2(0.0, 0.0), vec![(vec2(0.0, 0.0), vec2(500.0, 300.0), GREEN)]),
+            tick_at(vec2(100.0, -200.0), vec![(vec2(100.0, -200.0), vec2(-50.0, 0.0), RED), (vec2(1.0, 2.0), vec2(3.0, 4.0), CYAN)]),
+        ];
+        let svg = render_svg(&ticks, 1);
+
+        // From (-50, -200) to (500, 300), with a 100m margin, and y measured downwards
+        assert!(svg.contains("viewBox=\"-150 -400 750 700\""), "{}", svg);
+        assert!(svg.contains("<g transform=\"scale(1,-1)\">"));
+
+        // One line for each drawn segment, plus the heading
+        assert_eq!(svg.matches("<line ").count(), 3 + 1);
+        assert!(svg.contains("x1=\"0\" y1=\"0\" x2=\"500\" y2=\"300\" stroke=\"#00ff00\""));
+        assert!(svg.contains("stroke=\"#47cbe6\""));
+        assert!(svg.contains("points=\"0,0 100,-200\""));
+    }
+
+    #[test]
+    fn svg_only_draws_every_nth_tick_and_the_last() {
+        let ticks: Vec<TickOutputs> = (0..10)
+            .map(|i| tick_at(vec2(i as f64, 0.0), vec![(vec2(0.0, 0.0), vec2(1.0, 1.0), GREEN)]))
+            .collect();
+        // Ticks 0, 4 and 8, and the last one
+        assert_eq!(render_svg(&ticks, 4).matches("<line ").count(), 4 + 1);
+        assert!(render_svg(&[], 4).is_empty());
+    }
+
+    #[test]
+    fn recorded_config_has_every_key() {
+        let mut config = ShipConfig::for_class(Class::Frigate);
+        config.raw_radio_channel = None;
+        config.telemetry = Category::Fire as u32;
+        let line = record_config(&config);
+        for name in ShipConfig::KEYS {
+            assert!(line.contains(&format!(" {}=", name)), "{} missing from {}", name, line);
+        }
+        assert!(line.contains(" raw_radio_channel=-1"));
+        assert!(line.contains(" debug_fire=1") && line.contains(" debug_scan=0"));
+    }
+
+    // Sweeps
+
+    #[test]
+    fn sweep_grid_has_every_combination_in_order() {
+        let grid = sweep_grid(&[("max_range", vec![1000.0, 2000.0]), ("passing_speed", vec![100.0, 150.0, 200.0])]);
+        assert_eq!(grid.len(), 6);
+        assert_eq!(grid[0], vec![("max_range", 1000.0), ("passing_speed", 100.0)]);
+        assert_eq!(grid[1], vec![("max_range", 1000.0), ("passing_speed", 150.0)]);
+        assert_eq!(grid[5], vec![("max_range", 2000.0), ("passing_speed", 200.0)]);
+        assert_eq!(grid, sweep_grid(&[("max_range", vec![1000.0, 2000.0]), ("passing_speed", vec![100.0, 150.0, 200.0])]));
+    }
+
+    #[test]
+    fn sweep_random_repeats_for_the_same_seed() {
+        let ranges = [("max_range", 1000.0, 3000.0), ("nav_constant", 3.0, 5.0)];
+        let a = sweep_random(&ranges, 20, 42);
+        assert_eq!(a, sweep_random(&ranges, 20, 42));
+        assert_ne!(a, sweep_random(&ranges, 20, 43));
+
+        assert_eq!(a.len(), 20);
+        for config in &a {
+            assert_eq!(config.len(), 2);
+            assert!((1000.0..3000.0).contains(&config[0].1));
+            assert!((3.0..5.0).contains(&config[1].1));
+        }
+    }
+
+    #[test]
+    fn sweep_environment_is_one_override_per_line() {
+        assert_eq!(sweep_environment(&[("max_range", 2500.0), ("augmented_nav", 1.0)]), "max_range=2500\naugmented_nav=1\n");
+    }
+
+    // League
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn even_match_moves_ratings_by_half_of_k() {
+        let mut league = League::new(&["radio", "search"]);
+        league.record("radio", "search", MatchResult::Win);
+        assert!(close(league.rating("radio"), 1516.0));
+        assert!(close(league.rating("search"), 1484.0));
+
+        let mut league = League::new(&["radio", "search"]);
+        league.record("radio", "search", MatchResult::Draw);
+        assert!(close(league.rating("radio"), 1500.0));
+        assert!(close(league.rating("search"), 1500.0));
+    }
+
+    #[test]
+    fn upset_moves_ratings_further() {
+        let mut league = League::new(&["radio", "search"]);
+        league.ratings = vec![("radio".to_string(), 1900.0), ("search".to_string(), 1500.0)];
+
+        // 400 points ahead, so radio was expected to win 10 out of 11
+        league.record("radio", "search", MatchResult::Loss);
+        let change = 32.0 * 10.0 / 11.0;
+        assert!(close(league.rating("radio"), 1900.0 - change));
+        assert!(close(league.rating("search"), 1500.0 + change));
+    }
+
+    #[test]
+    fn ratings_survive_save_and_load() {
+        let bots = ["radio", "search", "radar"];
+        let mut league = League::new(&bots);
+        league.record("radio", "search", MatchResult::Win);
+        league.record("search", "radar", MatchResult::Draw);
+        league.record("radar", "radio", MatchResult::Loss);
+        league.record("radio", "radar", MatchResult::Win);
+
+        let loaded = League::load(&bots, &league.save());
+        assert_eq!(loaded.results, league.results);
+        for bot in bots {
+            assert!(close(loaded.rating(bot), league.rating(bot)));
+        }
+    }
+
+    #[test]
+    fn load_skips_lines_it_cannot_read() {
+        let loaded = League::load(&["radio", "search"], "radio search Win\nnot a match\n\nradio search Tie\n");
+        assert_eq!(loaded.results.len(), 1);
+    }
+}